@@ -1,9 +1,21 @@
+use std::collections::HashSet;
 use std::env::current_dir;
 use std::{fs::File, io::BufReader};
 
 use serde::Deserialize;
 
-use crate::monitor::website::{Get, RouteTest};
+use crate::monitor::dependencies::DependencyGraph;
+use crate::monitor::escalation::EscalationPolicy;
+use crate::monitor::routing::RoutingRule;
+use crate::monitor::website::assertions::ResponseAssertions;
+use crate::monitor::website::command::Command;
+use crate::monitor::website::dns::Dns;
+use crate::monitor::website::heartbeat::Heartbeat;
+use crate::monitor::website::scenario::Scenario;
+use crate::monitor::website::sitemap::Sitemap;
+use crate::monitor::website::ssl::Ssl;
+use crate::monitor::website::tcp::Tcp;
+use crate::monitor::website::{configured_checks, Check, RouteTest};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
@@ -11,15 +23,28 @@ pub struct Config {
     pub enable_service_monitor: Option<bool>,
     pub api_tests: Option<Vec<RouteTest>>,
     pub frontend_tests: Option<Vec<RouteTest>>,
-    pub ssl_tests: Option<Vec<Get>>,
+    pub ssl_tests: Option<Vec<Ssl>>,
+    pub tcp_tests: Option<Vec<Tcp>>,
+    pub dns_tests: Option<Vec<Dns>>,
+    pub scenario_tests: Option<Vec<Scenario>>,
+    pub sitemap_tests: Option<Vec<Sitemap>>,
+    pub heartbeats: Option<Vec<Heartbeat>>,
+    pub command_tests: Option<Vec<Command>>,
     pub website_monitor_timeout: Option<u64>,
     pub pause_reminder_timeout: Option<u64>,
     pub times_to_retry: Option<i64>,
+    pub max_concurrent_checks: Option<usize>,
+    pub max_concurrent_checks_per_host: Option<usize>,
+    pub flap_window: Option<usize>,
+    pub flap_high_threshold: Option<f64>,
+    pub flap_low_threshold: Option<f64>,
 
     // telegram
     pub enable_telegram: Option<bool>,
     pub telegram_bot_token: Option<String>,
     pub groups: Option<Vec<i64>>,
+    pub routing: Option<Vec<RoutingRule>>,
+    pub escalation_policies: Option<Vec<EscalationPolicy>>,
 
     // api
     pub enable_api: Option<bool>,
@@ -33,10 +58,10 @@ impl Config {
         let mut configs = Config::default();
 
         // todo: take this path from the application args
-        let path = String::from(format!(
+        let path = format!(
             "{}/config.json",
-            current_dir().unwrap().display().to_string()
-        ));
+            current_dir().unwrap().display()
+        );
         println!("path: {}", path);
 
         match File::open(path.clone()) {
@@ -57,9 +82,53 @@ impl Config {
         if configs.telegram_bot_token.is_none() {
             panic!("Telegram bot token wasn't set in the configurations.");
         }
+        if let Err(err) = configs.validate() {
+            panic!("Invalid configurations: {}", err);
+        }
         // println!("{:?}", configs);
 
-        return configs;
+        configs
+    }
+
+    /// Validate the references between the checks, the routing rules and the escalation policies.
+    fn validate(&self) -> Result<(), String> {
+        if self.flap_low_threshold > self.flap_high_threshold {
+            return Err("flap_low_threshold can't be greater than flap_high_threshold".to_string());
+        }
+
+        if self.website_monitor_timeout == Some(0) {
+            return Err("website_monitor_timeout should be at least 1 second".to_string());
+        }
+        let checks = configured_checks(self);
+        let mut check_ids = HashSet::new();
+        for check in checks.iter() {
+            if !check_ids.insert(check.id()) {
+                return Err(format!("duplicated check {}", check.id()));
+            }
+            if check.settings().interval == Some(0) {
+                return Err(format!("the interval of {} should be at least 1 second", check.id()));
+            }
+            let assertions: Vec<&ResponseAssertions> = match check {
+                Check::Route(_, test) => vec![test.assertions()],
+                Check::Scenario(scenario) => scenario.steps.iter().map(|step| &step.assertions).collect(),
+                _ => Vec::new(),
+            };
+            for assertion in assertions {
+                assertion.validate().map_err(|err| format!("{}: {}", check.id(), err))?;
+            }
+        }
+        DependencyGraph::new(&checks)?;
+
+        let policies: Vec<&String> = self.escalation_policies.iter().flatten().map(|policy| &policy.name).collect();
+        let escalations = checks.iter()
+            .filter_map(|check| check.settings().escalation.as_ref())
+            .chain(self.routing.iter().flatten().filter_map(|rule| rule.escalation.as_ref()));
+        for escalation in escalations {
+            if !policies.contains(&escalation) {
+                return Err(format!("unknown escalation policy {}", escalation));
+            }
+        }
+        Ok(())
     }
 
     fn merge_configs_with_defalt(mut config: Config) -> Config {
@@ -80,12 +149,45 @@ impl Config {
         if config.ssl_tests.is_none() {
             config.ssl_tests = default.ssl_tests;
         }
+        if config.tcp_tests.is_none() {
+            config.tcp_tests = default.tcp_tests;
+        }
+        if config.dns_tests.is_none() {
+            config.dns_tests = default.dns_tests;
+        }
+        if config.scenario_tests.is_none() {
+            config.scenario_tests = default.scenario_tests;
+        }
+        if config.sitemap_tests.is_none() {
+            config.sitemap_tests = default.sitemap_tests;
+        }
+        if config.heartbeats.is_none() {
+            config.heartbeats = default.heartbeats;
+        }
+        if config.command_tests.is_none() {
+            config.command_tests = default.command_tests;
+        }
         if config.pause_reminder_timeout.is_none() {
             config.pause_reminder_timeout = default.pause_reminder_timeout;
         }
         if config.times_to_retry.is_none() {
             config.times_to_retry = default.times_to_retry;
         }
+        if config.max_concurrent_checks.is_none() {
+            config.max_concurrent_checks = default.max_concurrent_checks;
+        }
+        if config.max_concurrent_checks_per_host.is_none() {
+            config.max_concurrent_checks_per_host = default.max_concurrent_checks_per_host;
+        }
+        if config.flap_window.is_none() {
+            config.flap_window = default.flap_window;
+        }
+        if config.flap_high_threshold.is_none() {
+            config.flap_high_threshold = default.flap_high_threshold;
+        }
+        if config.flap_low_threshold.is_none() {
+            config.flap_low_threshold = default.flap_low_threshold;
+        }
         // telegram
         if config.enable_telegram.is_none() {
             config.enable_telegram = default.enable_telegram;
@@ -93,6 +195,12 @@ impl Config {
         if config.groups.is_none() {
             config.groups = default.groups;
         }
+        if config.routing.is_none() {
+            config.routing = default.routing;
+        }
+        if config.escalation_policies.is_none() {
+            config.escalation_policies = default.escalation_policies;
+        }
         // api
         if config.host.is_none() {
             config.host = default.host;
@@ -106,10 +214,10 @@ impl Config {
         if config.enable_api.is_none() {
             config.enable_api = default.enable_api;
         }
-        return config;
+        config
     }
 
-    fn default() -> Config {
+    pub(crate) fn default() -> Config {
         Config {
             // service monitor
             enable_service_monitor: Some(true),
@@ -117,12 +225,25 @@ impl Config {
             frontend_tests: Some(Vec::new()),
             website_monitor_timeout: Some(20),
             ssl_tests: Some(Vec::new()),
+            tcp_tests: Some(Vec::new()),
+            dns_tests: Some(Vec::new()),
+            scenario_tests: Some(Vec::new()),
+            sitemap_tests: Some(Vec::new()),
+            heartbeats: Some(Vec::new()),
+            command_tests: Some(Vec::new()),
             pause_reminder_timeout: Some(86400),
             times_to_retry: Some(5),
+            max_concurrent_checks: Some(10),
+            max_concurrent_checks_per_host: Some(2),
+            flap_window: Some(20),
+            flap_high_threshold: Some(30.0),
+            flap_low_threshold: Some(10.0),
             // telegram
             enable_telegram: Some(true),
             telegram_bot_token: None,
             groups: Some(Vec::new()),
+            routing: Some(Vec::new()),
+            escalation_policies: Some(Vec::new()),
             // api
             host: Some("127.0.0.1".to_string()),
             port: Some(5353),
@@ -148,6 +269,22 @@ mod tests {
         assert_eq!(configs.api_tests.unwrap().len(), 2);
     }
 
+    #[test]
+    fn deserialize_check_settings_test() {
+        let json_example = "{\"telegram_bot_token\": \"123456:ABC-DEF1234ghIkl-zyx57W2v1u123ew11\", \"api_tests\": [{\"type\": \"GET\", \"url\": \"https://api.binarycoffee.dev/api/sitemap\", \"interval\": 10, \"jitter\": 5}], \"ssl_tests\": [{\"url\": \"binarycoffee.dev\", \"interval\": 3600, \"depends_on\": [\"GET https://api.binarycoffee.dev/api/sitemap\"]}]}".to_string();
+        let configs = Config::merge_configs_with_defalt(
+            serde_json::from_str(&json_example)
+                .expect("Error deserializing configuration json file."),
+        );
+
+        let api_tests = configs.api_tests.unwrap();
+        assert_eq!(api_tests[0].settings().interval, Some(10));
+        assert_eq!(api_tests[0].settings().jitter, Some(5));
+        let ssl_tests = configs.ssl_tests.unwrap();
+        assert_eq!(ssl_tests[0].settings.interval, Some(3600));
+        assert_eq!(ssl_tests[0].settings.depends_on, Some(vec!["GET https://api.binarycoffee.dev/api/sitemap".to_string()]));
+    }
+
     #[test]
     fn merge_configs_test() {
         let mut config = Config {
@@ -156,13 +293,26 @@ mod tests {
             api_tests: None,
             frontend_tests: None,
             ssl_tests: None,
+            tcp_tests: None,
+            dns_tests: None,
+            scenario_tests: None,
+            sitemap_tests: None,
+            heartbeats: None,
+            command_tests: None,
             website_monitor_timeout: None,
             pause_reminder_timeout: None,
             times_to_retry: None,
+            max_concurrent_checks: None,
+            max_concurrent_checks_per_host: None,
+            flap_window: None,
+            flap_high_threshold: None,
+            flap_low_threshold: None,
             // telegram
             enable_telegram: None,
             telegram_bot_token: Some("asdfasdf.asdfasdf".to_string()),
             groups: None,
+            routing: None,
+            escalation_policies: None,
             // api
             host: None,
             port: None,
@@ -177,17 +327,76 @@ mod tests {
         assert!(config.api_tests.is_some());
         assert!(config.frontend_tests.is_some());
         assert!(config.ssl_tests.is_some());
+        assert!(config.tcp_tests.is_some());
+        assert!(config.dns_tests.is_some());
+        assert!(config.scenario_tests.is_some());
+        assert!(config.sitemap_tests.is_some());
+        assert!(config.heartbeats.is_some());
+        assert!(config.command_tests.is_some());
         assert!(config.website_monitor_timeout.is_some());
         assert!(config.pause_reminder_timeout.is_some());
         assert!(config.times_to_retry.is_some());
+        assert!(config.max_concurrent_checks.is_some());
+        assert!(config.max_concurrent_checks_per_host.is_some());
+        assert!(config.flap_window.is_some());
+        assert!(config.flap_high_threshold.is_some());
+        assert!(config.flap_low_threshold.is_some());
         // telegram
         assert!(config.enable_telegram.is_some());
         assert!(config.telegram_bot_token.is_some());
         assert!(config.groups.is_some());
+        assert!(config.routing.is_some());
+        assert!(config.escalation_policies.is_some());
         // api
         assert!(config.host.is_some());
         assert!(config.port.is_some());
         assert!(config.api_token.is_some());
         assert!(config.enable_api.is_some());
     }
+
+    #[test]
+    fn validate_references_test() {
+        let json_example = "{\"telegram_bot_token\": \"123456:ABC-DEF1234ghIkl-zyx57W2v1u123ew11\", \"api_tests\": [{\"type\": \"GET\", \"url\": \"https://api.binarycoffee.dev/api/sitemap\", \"escalation\": \"on-call\"}], \"routing\": [{\"tags\": [\"database\"], \"chats\": [-100], \"escalation\": \"dba\"}], \"escalation_policies\": [{\"name\": \"on-call\", \"steps\": [{\"after\": 10}, {\"after\": 20, \"chats\": [-200]}]}]}".to_string();
+        let mut configs = Config::merge_configs_with_defalt(
+            serde_json::from_str(&json_example)
+                .expect("Error deserializing configuration json file."),
+        );
+
+        assert_eq!(configs.validate(), Err("unknown escalation policy dba".to_string()));
+        configs.routing = Some(Vec::new());
+        assert_eq!(configs.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_checks_test() {
+        let json_example = "{\"telegram_bot_token\": \"123456:ABC-DEF1234ghIkl-zyx57W2v1u123ew11\", \"api_tests\": [{\"type\": \"GET\", \"url\": \"https://binarycoffee.dev\"}], \"frontend_tests\": [{\"type\": \"GET\", \"url\": \"https://binarycoffee.dev\", \"interval\": 0}]}".to_string();
+        let mut configs = Config::merge_configs_with_defalt(
+            serde_json::from_str(&json_example)
+                .expect("Error deserializing configuration json file."),
+        );
+
+        assert_eq!(configs.validate(), Err("duplicated check GET https://binarycoffee.dev".to_string()));
+        configs.api_tests = Some(Vec::new());
+        assert_eq!(configs.validate(), Err("the interval of GET https://binarycoffee.dev should be at least 1 second".to_string()));
+        configs.frontend_tests = Some(Vec::new());
+        assert_eq!(configs.validate(), Ok(()));
+        configs.api_tests = Some(vec![serde_json::from_str("{\"type\": \"GET\", \"url\": \"https://binarycoffee.dev\", \"expected_status\": [\"300-200\"]}").unwrap()]);
+        assert_eq!(configs.validate(), Err("GET https://binarycoffee.dev: invalid expected status \"300-200\"".to_string()));
+        configs.api_tests = Some(Vec::new());
+        configs.website_monitor_timeout = Some(0);
+        assert_eq!(configs.validate(), Err("website_monitor_timeout should be at least 1 second".to_string()));
+    }
+
+    #[test]
+    fn validate_flap_thresholds_test() {
+        let json_example = "{\"telegram_bot_token\": \"123456:ABC-DEF1234ghIkl-zyx57W2v1u123ew11\", \"flap_high_threshold\": 20.0, \"flap_low_threshold\": 25.0}".to_string();
+        let mut configs = Config::merge_configs_with_defalt(
+            serde_json::from_str(&json_example)
+                .expect("Error deserializing configuration json file."),
+        );
+
+        assert_eq!(configs.validate(), Err("flap_low_threshold can't be greater than flap_high_threshold".to_string()));
+        configs.flap_low_threshold = Some(20.0);
+        assert_eq!(configs.validate(), Ok(()));
+    }
 }
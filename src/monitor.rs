@@ -1,17 +1,44 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use futures_util::FutureExt;
+use reqwest::Client;
 use tokio::sync::Mutex;
+use tokio::task::{AbortHandle, JoinSet};
 use tokio::time::sleep;
 
 use crate::config::Config;
 use crate::monitor::api::ApiService;
+use crate::monitor::dependencies::DependencyGraph;
+use crate::monitor::escalation::EscalationTracker;
+use crate::monitor::escalation::ClosedAlert;
+use crate::monitor::notification::{
+    alert_keyboard, dependents_message, flapping_message, result_message, transition_message,
+    unreachable_dependents_message, AlertAction,
+};
+use crate::monitor::routing::AlertRouter;
+use crate::monitor::scheduler::Scheduler;
+use crate::monitor::state::{CheckStatus, Confirmation, FlapDetector, Flapping, StateTracker, ThresholdTracker};
 use crate::monitor::telegram::TelegramService;
 use crate::monitor::telegram::TelegramServiceTrait;
+use crate::monitor::telegram::models::CallbackQuery;
 use crate::monitor::utils::ToMarkdown;
-use crate::monitor::website::WebsiteService;
+use crate::monitor::website::heartbeat::HeartbeatRegistry;
+use crate::monitor::website::models::CheckResult;
+use crate::monitor::website::{Check, WebsiteService};
+
+/// Time an alert isn't notified when it's muted from its buttons, it's notified again then if the check
+/// is still down.
+const MUTE_DURATION: Duration = Duration::from_secs(3600);
 
 pub mod api;
+pub mod dependencies;
+pub mod escalation;
+pub mod notification;
+pub mod routing;
+pub mod scheduler;
+pub mod state;
 pub mod telegram;
 pub mod website;
 pub mod utils;
@@ -20,30 +47,43 @@ pub struct Monitor {
     configs: Config,
     telegram_service: Arc<Mutex<dyn TelegramServiceTrait + Send>>,
     web_service: Arc<Mutex<WebsiteService>>,
+    heartbeats: HeartbeatRegistry,
+    escalations: Arc<Mutex<EscalationTracker>>,
 }
 
 /// This class introduces three key services: Telegram integration for communication, website
 /// monitoring for surveillance, and an API service for streamlined data access.
 impl Monitor {
     pub fn new(configs: Config, telegram_ins: Option<Arc<Mutex<dyn TelegramServiceTrait + Send>>>) -> Monitor {
-        let web = Arc::new(Mutex::new(WebsiteService::new(configs.clone())));
-        if telegram_ins.is_none() {
-            // telegram = Arc::new(Mutex::new(TelegramService::new(configs.clone())));
-            Monitor {
+        let web_service = WebsiteService::new(configs.clone());
+        let heartbeats = web_service.heartbeats();
+        let web = Arc::new(Mutex::new(web_service));
+        let escalations = Arc::new(Mutex::new(EscalationTracker::new(
+            configs.escalation_policies.as_deref().unwrap_or_default(),
+        )));
+        match telegram_ins {
+            None => Monitor {
                 configs: configs.clone(),
                 web_service: web.clone(),
                 telegram_service: Arc::new(Mutex::new(TelegramService::new(configs))),
-            }
-        } else {
-            // telegram = telegram_ins.unwrap();
-            Monitor {
-                configs: configs,
+                heartbeats,
+                escalations,
+            },
+            Some(telegram) => Monitor {
+                configs,
                 web_service: web.clone(),
-                telegram_service: telegram_ins.unwrap(),
-            }
+                telegram_service: telegram,
+                heartbeats,
+                escalations,
+            },
         }
     }
 
+    /// Tracker of the open alerts, shared by the website and the telegram monitors.
+    pub fn escalations(&self) -> Arc<Mutex<EscalationTracker>> {
+        self.escalations.clone()
+    }
+
     pub async fn start(&self) {
         let pause = Arc::new(Mutex::new(false));
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -52,6 +92,7 @@ impl Monitor {
         let pause_ref = pause.clone();
         let telegram_service_ref = self.telegram_service.clone();
         let web_service_ref = self.web_service.clone();
+        let escalations_ref = self.escalations.clone();
         let config_ref = self.configs.clone();
         let telegram_monitor_thread = rt.spawn(async move {
             if config_ref.enable_telegram.unwrap() {
@@ -59,6 +100,7 @@ impl Monitor {
                     telegram_service_ref,
                     web_service_ref,
                     pause_ref,
+                    escalations_ref,
                 );
                 telegram_monitor.start_monitoring().await
             }
@@ -69,9 +111,10 @@ impl Monitor {
         let pause_ref = pause.clone();
         let telegram_service_ref = self.telegram_service.clone();
         let web_service_ref = self.web_service.clone();
+        let escalations_ref = self.escalations.clone();
         let website_monitor = rt.spawn(async move {
             if config_ref.enable_service_monitor.unwrap() {
-                let web_monitor = WebMonitor::new(config_ref, telegram_service_ref, web_service_ref, pause_ref);
+                let mut web_monitor = WebMonitor::new(config_ref, telegram_service_ref, web_service_ref, pause_ref, escalations_ref);
                 web_monitor.run_website_monitor().await;
             }
         });
@@ -79,9 +122,10 @@ impl Monitor {
         // start api service
         let config_ref = self.configs.clone();
         let telegram_service_ref = self.telegram_service.clone();
+        let heartbeats_ref = self.heartbeats.clone();
         let api_thread = rt.spawn(async move {
             if config_ref.enable_api.unwrap() {
-                let api_service = ApiService::new(config_ref, telegram_service_ref);
+                let api_service = ApiService::new(config_ref, telegram_service_ref, heartbeats_ref);
                 api_service.start_api(None).await;
             }
         });
@@ -97,15 +141,75 @@ struct WebMonitor {
     web: Arc<Mutex<WebsiteService>>,
     pause_service: Arc<Mutex<bool>>,
     validator: Arc<Mutex<Validator>>,
+    state: StateTracker,
+    thresholds: ThresholdTracker,
+    flapping: FlapDetector,
+    dependencies: DependencyGraph,
+    router: AlertRouter,
+    escalations: Arc<Mutex<EscalationTracker>>,
 }
 
 impl WebMonitor {
-    pub fn new(configs: Config, telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>, web: Arc<Mutex<WebsiteService>>, pause_service: Arc<Mutex<bool>>) -> WebMonitor {
+    pub fn new(
+        configs: Config,
+        telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>,
+        web: Arc<Mutex<WebsiteService>>,
+        pause_service: Arc<Mutex<bool>>,
+        escalations: Arc<Mutex<EscalationTracker>>,
+    ) -> WebMonitor {
         let validator = Arc::new(Mutex::new(Validator::new(telegram.clone(), web.clone())));
-        WebMonitor { configs, telegram, web, pause_service, validator }
+        let flapping = FlapDetector::new(
+            configs.flap_window.unwrap(),
+            configs.flap_high_threshold.unwrap(),
+            configs.flap_low_threshold.unwrap(),
+        );
+        WebMonitor {
+            configs,
+            telegram,
+            web,
+            pause_service,
+            validator,
+            state: StateTracker::new(),
+            thresholds: ThresholdTracker::new(),
+            flapping,
+            dependencies: DependencyGraph::default(),
+            router: AlertRouter::default(),
+            escalations,
+        }
     }
 
-    pub async fn run_website_monitor(&self) {
+    pub async fn run_website_monitor(&mut self) {
+        let default_interval = self.configs.website_monitor_timeout.unwrap();
+        let checks = self.web.lock().await.checks();
+        // the dependencies are validated when the configurations are loaded
+        self.dependencies = DependencyGraph::new(&checks).unwrap_or_default();
+        self.router = AlertRouter::new(
+            self.configs.routing.as_deref().unwrap_or_default(),
+            &checks,
+            self.configs.groups.clone().unwrap_or_default(),
+        );
+        let scheduled = checks.iter()
+            .map(|check| {
+                let settings = check.settings();
+                self.state.set_confirmation(&check.id(), Confirmation {
+                    fail: settings.fail_threshold.unwrap_or(1),
+                    recover: settings.recover_threshold.unwrap_or(1),
+                });
+                (
+                    check.id(),
+                    Duration::from_secs(settings.interval.unwrap_or(default_interval)),
+                    Duration::from_secs(settings.jitter.unwrap_or(0)),
+                )
+            })
+            .collect();
+        let mut scheduler = Scheduler::new(scheduled, Instant::now());
+        let checks: HashMap<String, Check> = checks.into_iter().map(|check| (check.id(), check)).collect();
+
+        // the checks run in their own tasks, so a slow check doesn't delay the others or lock the service
+        let web = Arc::new(self.web.lock().await.clone());
+        let client = Client::new();
+        let mut running: JoinSet<CheckResult> = JoinSet::new();
+        let mut handles: HashMap<String, AbortHandle> = HashMap::new();
         let mut pause_time_ac = 0;
         loop {
             if pause_time_ac >= self.configs.pause_reminder_timeout.unwrap() {
@@ -116,22 +220,221 @@ impl WebMonitor {
                 ).await;
             }
 
-            let pause_v = self.pause_service.lock().await;
-            if !*pause_v {
-                let errors = self.web.lock().await.summary().await;
+            let paused = *self.pause_service.lock().await;
+            if !paused {
+                handles.retain(|_, handle| !handle.is_finished());
+                for check_id in scheduler.due(Instant::now()) {
+                    // a check isn't executed again until its previous execution finishes
+                    if handles.contains_key(&check_id) {
+                        continue;
+                    }
+                    let Some(check) = checks.get(&check_id).cloned() else {
+                        continue;
+                    };
+                    let (web, client) = (web.clone(), client.clone());
+                    let handle = running.spawn(async move { web.run_scheduled_check(&check, &client).await });
+                    handles.insert(check_id, handle);
+                }
+                self.notify_escalations().await;
+            } else {
+                pause_time_ac += default_interval;
+            }
 
-                if !errors.is_empty() {
-                    for err in errors.iter() {
-                        println!("Err: {}", err);
+            let wait = if paused {
+                Duration::from_secs(default_interval)
+            } else {
+                let now = Instant::now();
+                let next_escalation = self.escalations.lock().await.time_to_next(now);
+                scheduler.time_to_next(now).into_iter().chain(next_escalation)
+                    .min()
+                    .unwrap_or(Duration::from_secs(default_interval))
+            };
+            // wait until the next check is due or a running check finishes, the checks that finish at the
+            // same time are notified together
+            tokio::select! {
+                Some(finished) = running.join_next() => {
+                    let mut finished = vec![finished];
+                    while let Some(Some(result)) = running.join_next().now_or_never() {
+                        finished.push(result);
                     }
+                    let results = finished.into_iter()
+                        .filter_map(|result| result.map_err(|err| println!("Error running a check: {:?}", err)).ok())
+                        .collect();
+                    self.notify_transitions(results).await;
+                }
+                _ = sleep(wait) => {}
+            }
+        }
+    }
 
-                    self.validator.lock().await.handler_validation(errors, None, None).await;
+    async fn notify_transitions(&mut self, mut results: Vec<CheckResult>) {
+        // the parents first, so their status is known when their dependents are processed
+        results.sort_by_key(|result| self.dependencies.depth(&result.check_id));
+
+        let mut report: Vec<(String, String)> = Vec::new();
+        // checks that went DOWN, their alerts are escalated until they are acknowledged. The message of the
+        // result is the one of the incident when the check isn't notified (ex: while it's flapping)
+        let mut alerts: Vec<(String, String)> = Vec::new();
+        // dependents that became unreachable or recovered, folded into the message of their parent
+        let mut folded: HashMap<String, (Vec<String>, Vec<String>)> = HashMap::new();
+        // dependents that became unreachable after the alert of their parent was notified, they are
+        // notified together by parent
+        let mut unreachable_by_parent: Vec<(String, Vec<String>)> = Vec::new();
+        for mut result in results {
+            if let Some(ref err) = result.error {
+                println!("Err: {}: {}", result.check_id, err);
+            }
+            let failed_parent = match result.is_ok() {
+                true => None,
+                false => self.dependencies.failed_parent(&result.check_id, &self.state).map(str::to_string),
+            };
+            if let Some(ref parent) = failed_parent {
+                result = result.unreachable(parent);
+            }
+
+            // the messages of a muted incident aren't notified until its mute expires, except its recovery
+            let muted = self.escalations.lock().await.is_muted(&result.check_id, Instant::now());
+            let flapping = self.flapping.update(&result.check_id, result.status);
+            let transition = self.state.update(&result.check_id, result.status, result.timestamp);
+            let resolved = transition.as_ref().is_some_and(|transition| transition.from == CheckStatus::Down);
+            // a failure without certificate (ex: connection error) doesn't reset the notified thresholds
+            let new_threshold = match result.certificate {
+                Some(ref cert) => self.thresholds.update(&result.check_id, cert.warning_threshold),
+                None => false,
+            };
+
+            // the incidents are opened and resolved even when the message isn't notified
+            match transition.as_ref().map(|transition| (transition.from, transition.to)) {
+                Some((_, CheckStatus::Down)) => alerts.push((result.check_id.clone(), result_message(&result))),
+                Some((CheckStatus::Down, _)) => self.escalations.lock().await.resolve(&result.check_id),
+                _ => {}
+            }
+            // a check that stops flapping while it's DOWN is alerted if its incident isn't open
+            if let Some(Flapping::Stopped(_)) = flapping {
+                let down = self.state.get(&result.check_id).is_some_and(|state| state.status == CheckStatus::Down);
+                let open = alerts.iter().any(|(check_id, _)| *check_id == result.check_id)
+                    || self.escalations.lock().await.is_open(&result.check_id);
+                if down && !open {
+                    alerts.push((result.check_id.clone(), result_message(&result)));
                 }
+            }
+
+            let message = if let Some(flapping) = flapping {
+                Some(flapping_message(&flapping, &result))
+            } else if let Some(transition) = transition {
+                let dependent = transition.to == CheckStatus::Unreachable
+                    || (transition.from == CheckStatus::Unreachable && transition.to == CheckStatus::Up);
+                let notified_parent = self.dependencies.ancestors(&result.check_id).into_iter()
+                    .find(|ancestor| report.iter().any(|(check_id, _)| check_id == ancestor));
+                match (dependent, notified_parent, failed_parent) {
+                    // the transitions of a flapping check are summarized by the flapping notifications
+                    _ if self.flapping.is_flapping(&result.check_id) => None,
+                    (true, Some(parent), _) => {
+                        let (unreachable, recovered) = folded.entry(parent.to_string()).or_default();
+                        match transition.to {
+                            CheckStatus::Unreachable => unreachable.push(result.check_id.clone()),
+                            _ => recovered.push(result.check_id.clone()),
+                        }
+                        None
+                    }
+                    (true, None, Some(parent)) => {
+                        // while the failure of the parent isn't confirmed, the dependent is folded into its
+                        // alert once it's notified
+                        let parent_failing = matches!(
+                            self.state.get(&parent).map(|state| state.status),
+                            Some(CheckStatus::Down | CheckStatus::Unreachable)
+                        );
+                        if parent_failing {
+                            match unreachable_by_parent.iter_mut().find(|(failed, _)| *failed == parent) {
+                                Some((_, dependents)) => dependents.push(result.check_id.clone()),
+                                None => unreachable_by_parent.push((parent, vec![result.check_id.clone()])),
+                            }
+                        }
+                        None
+                    }
+                    _ => Some(transition_message(&transition, &result)),
+                }
+            } else if new_threshold {
+                Some(result_message(&result))
             } else {
-                pause_time_ac += self.configs.website_monitor_timeout.unwrap();
+                None
+            };
+            if let Some(message) = message.filter(|_| !muted || resolved) {
+                report.push((result.check_id.clone(), message));
+            }
+        }
+
+        // the alerts also list the dependents that became unreachable in previous executions, while the
+        // failure of the check wasn't confirmed
+        for (check_id, _) in alerts.iter() {
+            let (unreachable, recovered) = folded.entry(check_id.clone()).or_default();
+            for dependent in self.dependencies.dependents(check_id) {
+                let is_unreachable = self.state.get(dependent)
+                    .is_some_and(|state| state.status == CheckStatus::Unreachable);
+                if is_unreachable && !unreachable.iter().chain(recovered.iter()).any(|folded| folded == dependent) {
+                    unreachable.push(dependent.to_string());
+                }
             }
+        }
 
-            sleep(Duration::from_secs(self.configs.website_monitor_timeout.unwrap())).await;
+        // the messages are grouped by the chats they are routed to, the chats of the folded dependents
+        // also receive the message of their parent
+        let mut routed: Vec<(Vec<i64>, Vec<String>)> = Vec::new();
+        for (check_id, message) in report {
+            let (message, chats) = match folded.get(&check_id) {
+                Some((unreachable, recovered)) => {
+                    let check_ids: Vec<&str> = [check_id.as_str()].into_iter()
+                        .chain(unreachable.iter().chain(recovered.iter()).map(String::as_str))
+                        .collect();
+                    (format!("{}{}", message, dependents_message(unreachable, recovered)), self.router.chats(&check_ids))
+                }
+                None => (message, self.router.chats(&[&check_id])),
+            };
+            // the DOWN alerts are sent alone, with the buttons to acknowledge them
+            if let Some(index) = alerts.iter().position(|(alert, _)| *alert == check_id) {
+                alerts.remove(index);
+                let policy = self.router.escalation(&check_id);
+                let incident = self.escalations.lock().await.open(&check_id, policy, chats.clone(), message.clone(), Instant::now());
+                let sent = self.telegram.lock().await
+                    .send_message_with_keyboard(message, &Some(chats), alert_keyboard(incident))
+                    .await;
+                self.escalations.lock().await.add_messages(incident, sent);
+                continue;
+            }
+            WebMonitor::route(&mut routed, chats, message);
+        }
+        for (parent, dependents) in unreachable_by_parent {
+            let check_ids: Vec<&str> = dependents.iter().map(String::as_str).collect();
+            let chats = self.router.chats(&check_ids);
+            WebMonitor::route(&mut routed, chats, unreachable_dependents_message(&parent, &dependents));
+        }
+        // the incidents of the checks that weren't notified are opened without message, so they are escalated
+        for (check_id, message) in alerts {
+            let policy = self.router.escalation(&check_id);
+            let chats = self.router.chats(&[&check_id]);
+            self.escalations.lock().await.open(&check_id, policy, chats, message, Instant::now());
+        }
+        for (chats, messages) in routed {
+            self.validator.lock().await.handler_validation(messages, None, Some(chats)).await;
+        }
+    }
+
+    /// Add the message to the messages of the chats, the messages of the same chats are sent together.
+    fn route(routed: &mut Vec<(Vec<i64>, Vec<String>)>, chats: Vec<i64>, message: String) {
+        match routed.iter_mut().find(|(group_chats, _)| *group_chats == chats) {
+            Some((_, messages)) => messages.push(message),
+            None => routed.push((chats, vec![message])),
+        }
+    }
+
+    /// Notify the steps of the escalations of the unacknowledged alerts.
+    async fn notify_escalations(&mut self) {
+        let escalations = self.escalations.lock().await.due(Instant::now());
+        for escalation in escalations {
+            let sent = self.telegram.lock().await
+                .send_message_with_keyboard(escalation.message, &Some(escalation.chats), alert_keyboard(escalation.incident))
+                .await;
+            self.escalations.lock().await.add_messages(escalation.incident, sent);
         }
     }
 }
@@ -140,12 +443,18 @@ struct TelegramMonitor {
     telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>,
     pause_service: Arc<Mutex<bool>>,
     validator: Arc<Mutex<Validator>>,
+    escalations: Arc<Mutex<EscalationTracker>>,
 }
 
 impl TelegramMonitor {
-    pub fn new(telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>, web: Arc<Mutex<WebsiteService>>, pause_service: Arc<Mutex<bool>>) -> TelegramMonitor {
+    pub fn new(
+        telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>,
+        web: Arc<Mutex<WebsiteService>>,
+        pause_service: Arc<Mutex<bool>>,
+        escalations: Arc<Mutex<EscalationTracker>>,
+    ) -> TelegramMonitor {
         let validator = Arc::new(Mutex::new(Validator::new(telegram.clone(), web.clone())));
-        TelegramMonitor { telegram, pause_service, validator }
+        TelegramMonitor { telegram, pause_service, validator, escalations }
     }
 
     pub async fn start_monitoring(&self) {
@@ -168,13 +477,16 @@ impl TelegramMonitor {
                 println!("{:?}", updates);
             }
             for update in updates {
+                if let Some(query) = update.callback_query {
+                    self.handle_alert_action(query).await;
+                }
                 if let Some(msg) = update.message {
                     if let Some(ent) = msg.entities {
                         let text = msg.text.unwrap();
                         let group_id = msg.chat.id;
 
                         for e in ent.iter() {
-                            if e.type_value == String::from("bot_command") {
+                            if e.type_value == "bot_command" {
                                 let offset_beg = e.offset as usize;
                                 let offset_end = (e.offset + e.length) as usize;
                                 let command_name = TelegramMonitor::extract_command(
@@ -188,6 +500,13 @@ impl TelegramMonitor {
                                         self.validator.lock().await.execute_check_api(group_id).await;
                                         self.validator.lock().await.execute_check_frontend(group_id).await;
                                         self.validator.lock().await.execute_check_certs(group_id).await;
+                                        self.validator.lock().await.execute_check_tcp(group_id).await;
+                                        self.validator.lock().await.execute_check_dns(group_id).await;
+                                        self.validator.lock().await.execute_check_scenarios(group_id).await;
+                                        self.validator.lock().await.execute_check_sitemaps(group_id).await;
+                                        self.validator.lock().await.execute_check_links(group_id).await;
+                                        self.validator.lock().await.execute_check_heartbeats(group_id).await;
+                                        self.validator.lock().await.execute_check_commands(group_id).await;
                                     }
                                     "/check_api" => {
                                         self.validator.lock().await.execute_check_api(group_id).await;
@@ -198,6 +517,37 @@ impl TelegramMonitor {
                                     "/check_certs" => {
                                         self.validator.lock().await.execute_check_certs(group_id).await;
                                     }
+                                    "/check_tcp" => {
+                                        self.validator.lock().await.execute_check_tcp(group_id).await;
+                                    }
+                                    "/check_dns" => {
+                                        self.validator.lock().await.execute_check_dns(group_id).await;
+                                    }
+                                    "/check_scenarios" => {
+                                        self.validator.lock().await.execute_check_scenarios(group_id).await;
+                                    }
+                                    "/check_sitemaps" => {
+                                        self.validator.lock().await.execute_check_sitemaps(group_id).await;
+                                    }
+                                    "/check_links" => {
+                                        self.validator.lock().await.execute_check_links(group_id).await;
+                                    }
+                                    "/check_heartbeats" => {
+                                        self.validator.lock().await.execute_check_heartbeats(group_id).await;
+                                    }
+                                    "/check_commands" => {
+                                        self.validator.lock().await.execute_check_commands(group_id).await;
+                                    }
+                                    "/ack" => {
+                                        // the id of the check is the rest of the message
+                                        let by = msg.from.as_ref().map(|user| user.display_name()).unwrap_or_default();
+                                        self.acknowledge(text[offset_end..].trim(), &by, group_id).await;
+                                    }
+                                    "/accept_content" => {
+                                        // the id of the check is the rest of the message
+                                        let check_id = text[offset_end..].trim();
+                                        self.validator.lock().await.accept_content(check_id, group_id).await;
+                                    }
                                     "/pause" => {
                                         let mut pause_v = self.pause_service.lock().await;
                                         *pause_v = true;
@@ -225,6 +575,83 @@ impl TelegramMonitor {
         }
     }
 
+    /// Stop the escalation of the alert of a check, or of all the alerts of the chat when the check is empty.
+    async fn acknowledge(&self, check_id: &str, by: &str, group_id: i64) {
+        let message = if check_id.is_empty() {
+            let alerts = self.escalations.lock().await.acknowledge_all(group_id, by);
+            self.update_alert_messages(&alerts, &format!("✅ Acknowledged by {}", by)).await;
+            if alerts.is_empty() {
+                "✅ There are no alerts to acknowledge.".to_string()
+            } else {
+                let check_ids: Vec<&str> = alerts.iter().map(|alert| alert.check_id.as_str()).collect();
+                format!("✅ Acknowledged the alerts of {}.", check_ids.join(", "))
+            }
+        } else {
+            let acknowledged = self.escalations.lock().await.acknowledge_in_chat(check_id, group_id, by);
+            match acknowledged {
+                Ok(alert) => {
+                    self.update_alert_messages(&[alert], &format!("✅ Acknowledged by {}", by)).await;
+                    format!("✅ Acknowledged the alert of {}.", check_id)
+                }
+                Err(err) => format!("⚠️ {}: {}.", check_id, err),
+            }
+        };
+        self.telegram.lock().await.send_message(message.parse_text_to_markdown(), &Some(vec![group_id])).await;
+    }
+
+    /// Execute the action of a button of a DOWN alert.
+    async fn handle_alert_action(&self, query: CallbackQuery) {
+        let Some((action, incident)) = query.data.as_deref().and_then(AlertAction::parse_callback_data) else {
+            self.telegram.lock().await.answer_callback_query(query.id, "Unknown action.".to_string()).await;
+            return;
+        };
+        let by = query.from.display_name();
+        println!("Alert action: {:?} of incident {} by {}", action, incident, by);
+
+        let answer = match action {
+            AlertAction::Acknowledge => {
+                let acknowledged = self.escalations.lock().await.acknowledge_incident(incident, &by);
+                acknowledged.map(|alert| (alert, format!("✅ Acknowledged by {}", by)))
+            }
+            AlertAction::Mute => {
+                let now = Instant::now();
+                let muted = self.escalations.lock().await.mute_incident(incident, now, now + MUTE_DURATION);
+                muted.map(|alert| (alert, format!("🔕 Muted for 1h by {}", by)))
+            }
+            AlertAction::Recheck => {
+                let check_id = self.escalations.lock().await.check_id(incident);
+                let answer = match check_id {
+                    Some(ref check_id) => format!("Checking {}...", check_id),
+                    None => "The alert is not open.".to_string(),
+                };
+                self.telegram.lock().await.answer_callback_query(query.id, answer).await;
+                if let Some(check_id) = check_id {
+                    let group_ids = query.message.map(|message| vec![message.chat.id]);
+                    self.validator.lock().await.execute_check_by_id(&check_id, group_ids).await;
+                }
+                return;
+            }
+        };
+        let answer = match answer {
+            Ok((alert, note)) => {
+                self.update_alert_messages(&[alert], &note).await;
+                note
+            }
+            Err(err) => format!("⚠️ {}", err),
+        };
+        self.telegram.lock().await.answer_callback_query(query.id, answer).await;
+    }
+
+    /// Add a note to the messages of the alerts (who acknowledged them, ...), their buttons are removed.
+    async fn update_alert_messages(&self, alerts: &[ClosedAlert], note: &str) {
+        for alert in alerts {
+            let text = format!("{}\n{}", alert.message, note.to_string().parse_text_to_markdown());
+            for (chat_id, message_id) in alert.messages.iter() {
+                self.telegram.lock().await.edit_message(*chat_id, *message_id, text.clone()).await;
+            }
+        }
+    }
+
     fn extract_command(command: String) -> String {
         if let Some(index) = command.find('@') {
             return command[0..index].to_string();
@@ -244,27 +671,106 @@ impl Validator {
     }
 
     async fn execute_check_certs(&self, group_id: i64) {
-        let errs = self.web.lock().await.certificates_vitaly().await;
+        let results = self.web.lock().await.certificates_vitaly().await;
         self.handler_validation(
-            errs,
+            results.iter().map(result_message).collect(),
             Some("✅ Certificates are OK.".to_string().parse_text_to_markdown()),
             Some(vec![group_id]),
         ).await;
     }
 
     async fn execute_check_frontend(&self, group_id: i64) {
-        let errs = self.web.lock().await.frontend_vitaly().await;
+        let results = self.web.lock().await.frontend_vitaly().await;
         self.handler_validation(
-            errs,
+            Validator::failures(&results),
             Some("✅ Frontend is working fine.".to_string().parse_text_to_markdown()),
             Some(vec![group_id]),
         ).await;
     }
 
+    async fn execute_check_tcp(&self, group_id: i64) {
+        let results = self.web.lock().await.tcp_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ TCP services are working fine.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    async fn execute_check_dns(&self, group_id: i64) {
+        let results = self.web.lock().await.dns_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ DNS records are working fine.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    async fn execute_check_scenarios(&self, group_id: i64) {
+        let results = self.web.lock().await.scenarios_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ Scenarios are working fine.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    async fn execute_check_sitemaps(&self, group_id: i64) {
+        let results = self.web.lock().await.sitemaps_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ Sitemap pages are working fine.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    async fn execute_check_links(&self, group_id: i64) {
+        let results = self.web.lock().await.crawls_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ Links of the frontend pages are working fine.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    async fn execute_check_heartbeats(&self, group_id: i64) {
+        let results = self.web.lock().await.heartbeats_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ Heartbeats are arriving in time.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    async fn execute_check_commands(&self, group_id: i64) {
+        let results = self.web.lock().await.commands_vitaly().await;
+        self.handler_validation(
+            Validator::failures(&results),
+            Some("✅ Commands are working fine.".to_string().parse_text_to_markdown()),
+            Some(vec![group_id]),
+        ).await;
+    }
+
+    /// Execute one check and reply with its result.
+    async fn execute_check_by_id(&self, check_id: &str, group_ids: Option<Vec<i64>>) {
+        let results = self.web.lock().await.run_checks_by_id(&[check_id.to_string()]).await;
+        self.handler_validation(results.iter().map(result_message).collect(), None, group_ids).await;
+    }
+
+    /// Accept the content change of a check, the check recovers in its next execution.
+    async fn accept_content(&self, check_id: &str, group_id: i64) {
+        let message = if self.web.lock().await.accept_content(check_id) {
+            format!("✅ The next scheduled run of {} will set its new baseline.", check_id)
+        } else {
+            format!("⚠️ {} doesn't have a tracked content.", check_id)
+        };
+        self.telegram.lock().await.send_message(message.parse_text_to_markdown(), &Some(vec![group_id])).await;
+    }
+
     pub async fn execute_check_api(&self, group_id: i64) {
-        let errs = self.web.lock().await.api_vitally().await;
+        let results = self.web.lock().await.api_vitally().await;
         self.handler_validation(
-            errs,
+            Validator::failures(&results),
             Some("✅ Api is working fine.".to_string().parse_text_to_markdown()),
             Some(vec![group_id]),
         ).await;
@@ -290,12 +796,16 @@ impl Validator {
         }
     }
 
+    fn failures(results: &[CheckResult]) -> Vec<String> {
+        results.iter().filter(|result| !result.is_ok()).map(result_message).collect()
+    }
+
     fn handler_errors(errs: &Vec<String>, default: String) -> String {
-        if errs.len() > 0 {
+        if !errs.is_empty() {
             let mut report = "".to_string();
             for err in errs {
-                report.push_str(&err);
-                report.push_str("\n");
+                report.push_str(err);
+                report.push('\n');
             }
             return report;
         }
@@ -305,7 +815,20 @@ impl Validator {
 
 #[cfg(test)]
 mod tests {
-    use super::{TelegramMonitor};
+    use std::sync::Arc;
+
+    use tokio::sync::Mutex;
+
+    use super::{TelegramMonitor, WebMonitor};
+    use crate::config::Config;
+    use crate::monitor::dependencies::DependencyGraph;
+    use crate::monitor::escalation::EscalationTracker;
+    use crate::monitor::routing::AlertRouter;
+    use crate::monitor::state::Confirmation;
+    use crate::monitor::telegram::MockTelegramServiceTrait;
+    use crate::monitor::website::models::{CheckKind, CheckResult, CheckSettings, FailureReason};
+    use crate::monitor::website::tcp::Tcp;
+    use crate::monitor::website::WebsiteService;
 
     #[test]
     fn extract_command_test() {
@@ -318,4 +841,116 @@ mod tests {
             TelegramMonitor::extract_command("/check_all".to_string())
         );
     }
+
+    fn tcp(host: &str, depends_on: Option<Vec<String>>) -> Tcp {
+        Tcp {
+            host: host.to_string(),
+            port: 443,
+            settings: CheckSettings { depends_on, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn result(host: &str) -> CheckResult {
+        CheckResult::new(format!("TCP {}:443", host), CheckKind::Tcp, format!("{}:443", host))
+    }
+
+    fn down(host: &str) -> CheckResult {
+        result(host).fail(FailureReason::Request, "connection refused".to_string())
+    }
+
+    /// Monitor of the configured checks whose sent messages are registered, the alerts with the `alert: ` prefix.
+    fn web_monitor(configs: Config, messages: Arc<std::sync::Mutex<Vec<String>>>) -> WebMonitor {
+        let mut telegram = MockTelegramServiceTrait::new();
+        let messages_ref = messages.clone();
+        telegram.expect_send_message_with_keyboard().returning(move |text, _, _| {
+            messages_ref.lock().unwrap().push(format!("alert: {}", text));
+            vec![(-100, 7)]
+        });
+        telegram.expect_send_message().returning(move |text, _| messages.lock().unwrap().push(text));
+
+        let web = WebsiteService::new(configs.clone());
+        let checks = web.checks();
+        let mut monitor = WebMonitor::new(
+            configs,
+            Arc::new(Mutex::new(telegram)),
+            Arc::new(Mutex::new(web)),
+            Arc::new(Mutex::new(false)),
+            Arc::new(Mutex::new(EscalationTracker::new(&[]))),
+        );
+        monitor.dependencies = DependencyGraph::new(&checks).unwrap();
+        monitor.router = AlertRouter::new(&[], &checks, vec![-100]);
+        monitor
+    }
+
+    #[tokio::test]
+    async fn dependents_are_folded_into_the_alert_of_their_parent_test() {
+        let db = vec!["TCP db:443".to_string()];
+        let configs = Config {
+            tcp_tests: Some(vec![tcp("db", None), tcp("api", Some(db.clone())), tcp("web", Some(db))]),
+            groups: Some(vec![-100]),
+            ..Config::default()
+        };
+        let messages: Arc<std::sync::Mutex<Vec<String>>> = Arc::default();
+        let mut monitor = web_monitor(configs, messages.clone());
+        monitor.state.set_confirmation("TCP db:443", Confirmation { fail: 2, recover: 1 });
+
+        // the failure of the parent isn't confirmed yet, but the dependent isn't alerted
+        monitor.notify_transitions(vec![down("db")]).await;
+        monitor.notify_transitions(vec![down("api")]).await;
+        assert!(messages.lock().unwrap().is_empty());
+
+        monitor.notify_transitions(vec![down("db")]).await;
+        assert_eq!(*messages.lock().unwrap(), vec![
+            "alert: ❌ TCP db:443 fails: connection refused\\.\n⛔ Unreachable: TCP api:443".to_string(),
+        ]);
+
+        monitor.notify_transitions(vec![down("web")]).await;
+        assert_eq!(
+            messages.lock().unwrap()[1],
+            "⛔ Unreachable because TCP db:443 is down: TCP web:443\\.\n",
+        );
+    }
+
+    #[tokio::test]
+    async fn flapping_checks_open_their_incidents_test() {
+        let configs = Config {
+            tcp_tests: Some(vec![tcp("db", None)]),
+            groups: Some(vec![-100]),
+            flap_window: Some(4),
+            flap_high_threshold: Some(90.0),
+            flap_low_threshold: Some(10.0),
+            ..Config::default()
+        };
+        let messages: Arc<std::sync::Mutex<Vec<String>>> = Arc::default();
+        let mut monitor = web_monitor(configs, messages.clone());
+        let is_open = |monitor: &WebMonitor| monitor.escalations.try_lock().unwrap().is_open("TCP db:443");
+
+        monitor.notify_transitions(vec![result("db")]).await;
+        monitor.notify_transitions(vec![down("db")]).await;
+        monitor.notify_transitions(vec![result("db")]).await;
+        assert!(!is_open(&monitor));
+        messages.lock().unwrap().clear();
+
+        // the check starts flapping when it goes DOWN, the flapping message is the alert
+        monitor.notify_transitions(vec![down("db")]).await;
+        assert!(is_open(&monitor));
+        assert!(messages.lock().unwrap()[0].starts_with("alert: 🔀 TCP db:443 is flapping"));
+
+        // the transitions to DOWN while it's flapping aren't notified, but the incident is opened
+        monitor.notify_transitions(vec![result("db")]).await;
+        assert!(!is_open(&monitor));
+        monitor.notify_transitions(vec![down("db")]).await;
+        assert!(is_open(&monitor));
+        assert_eq!(messages.lock().unwrap().len(), 1);
+
+        // the check stops flapping while it's DOWN without incident
+        monitor.escalations.try_lock().unwrap().resolve("TCP db:443");
+        monitor.notify_transitions(vec![down("db")]).await;
+        monitor.notify_transitions(vec![down("db")]).await;
+        assert!(!is_open(&monitor));
+        monitor.notify_transitions(vec![down("db")]).await;
+        assert!(is_open(&monitor));
+        assert_eq!(messages.lock().unwrap()[1], "alert: TCP db:443 is stable again, it's ❌ DOWN\\.");
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::SystemTime;
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
 use reqwest::header::AUTHORIZATION;
@@ -11,21 +12,23 @@ use tokio::sync::oneshot::Receiver;
 use crate::config::Config;
 use crate::monitor::telegram::{TelegramServiceTrait};
 use crate::monitor::utils::ToMarkdown;
+use crate::monitor::website::heartbeat::{HeartbeatRegistry, Ping};
 
 pub struct ApiService {
     pub configs: Config,
     pub telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>,
+    pub heartbeats: HeartbeatRegistry,
 }
 
 impl ApiService {
-    pub fn new(configs: Config, telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>) -> ApiService {
-        ApiService { configs, telegram }
+    pub fn new(configs: Config, telegram: Arc<Mutex<dyn TelegramServiceTrait + Send>>, heartbeats: HeartbeatRegistry) -> ApiService {
+        ApiService { configs, telegram, heartbeats }
     }
 
     pub async fn start_api(&self, kill_receiver: Option<Receiver<()>>) {
         let addr_str = format!("{}:{}", self.configs.clone().host.unwrap(), self.configs.clone().port.unwrap());
         let addr: SocketAddr = addr_str.parse().unwrap();
-        println!("Server started in host: {}", addr.to_string());
+        println!("Server started in host: {}", addr);
 
         match kill_receiver {
             None => {
@@ -42,7 +45,7 @@ impl ApiService {
     }
 
     pub fn routes(&self) -> impl Filter<Extract=impl warp::Reply, Error=warp::Rejection> + Clone {
-        self.post_notification()
+        self.post_notification().or(self.post_heartbeat())
     }
 
     pub fn post_notification(&self) -> impl Filter<Extract=impl warp::Reply, Error=warp::Rejection> + Clone {
@@ -76,6 +79,46 @@ impl ApiService {
             })
     }
 
+    /// Pings of the heartbeats: `/heartbeat/{name}` when the job finished successfully, and
+    /// `/heartbeat/{name}/start` or `/heartbeat/{name}/fail` when it starts or fails.
+    pub fn post_heartbeat(&self) -> impl Filter<Extract=impl warp::Reply, Error=warp::Rejection> + Clone {
+        let auth_token = self.configs.clone().api_token.unwrap();
+        let heartbeats = self.heartbeats.clone();
+
+        let success = warp::path!("heartbeat" / String)
+            .map(|name: String| (name, "success".to_string()));
+        let event = warp::path!("heartbeat" / String / String)
+            .map(|name: String, event: String| (name, event));
+
+        success.or(event).unify()
+            .and(warp::post())
+            .and(warp::header::<String>(AUTHORIZATION.as_str()))
+            // inject auth token
+            .and(warp::any().map(move || auth_token.clone()))
+            // inject heartbeats registry
+            .and(warp::any().map(move || heartbeats.clone()))
+            .then(|(name, event): (String, String), token: String, auth_token: String, heartbeats: HeartbeatRegistry| async move {
+                // validate access token
+                if !ApiService::validate_auth(&auth_token, &token) {
+                    return warp::reply::with_status("FORBIDDEN", warp::http::StatusCode::FORBIDDEN);
+                }
+
+                let ping = match event.as_str() {
+                    "success" => Ping::Success,
+                    "start" => Ping::Start,
+                    "fail" => Ping::Fail,
+                    _ => return warp::reply::with_status("NOT FOUND", warp::http::StatusCode::NOT_FOUND),
+                };
+                match heartbeats.ping(&name, ping, SystemTime::now()) {
+                    Ok(()) => {
+                        println!("Heartbeat ping: {} ({:?})", name, ping);
+                        warp::reply::with_status("OK", warp::http::StatusCode::OK)
+                    }
+                    Err(_) => warp::reply::with_status("NOT FOUND", warp::http::StatusCode::NOT_FOUND),
+                }
+            })
+    }
+
     fn validate_auth(api_token: &str, base64_token: &str) -> bool {
         let base64_token = base64_token.trim();
 
@@ -88,7 +131,7 @@ impl ApiService {
             return false;
         }
 
-        match BASE64_STANDARD.decode(&base64_token[e.unwrap()..].trim()) {
+        match BASE64_STANDARD.decode(base64_token[e.unwrap()..].trim()) {
             Ok(token) => {
                 api_token.eq(&String::from_utf8(token).unwrap())
             }
@@ -113,16 +156,16 @@ mod tests {
     #[test]
     fn validate_auth_token_test() {
         // valid tokens
-        assert_eq!(true, ApiService::validate_auth("test", "Basic dGVzdA=="));
-        assert_eq!(true, ApiService::validate_auth("test", " Basic dGVzdA=="));
-        assert_eq!(true, ApiService::validate_auth("test", " Basic  dGVzdA==  "));
+        assert!(ApiService::validate_auth("test", "Basic dGVzdA=="));
+        assert!(ApiService::validate_auth("test", " Basic dGVzdA=="));
+        assert!(ApiService::validate_auth("test", " Basic  dGVzdA==  "));
 
         // invalid tokens
-        assert_eq!(false, ApiService::validate_auth("test", "dGVzdA==  "));
-        assert_eq!(false, ApiService::validate_auth("test", "Basi cdGVzdA=="));
-        assert_eq!(false, ApiService::validate_auth("tests", "Basic dGVzdA=="));
+        assert!(!ApiService::validate_auth("test", "dGVzdA==  "));
+        assert!(!ApiService::validate_auth("test", "Basi cdGVzdA=="));
+        assert!(!ApiService::validate_auth("tests", "Basic dGVzdA=="));
 
         // invalid base64
-        assert_eq!(false, ApiService::validate_auth("tests", "Basic cdGVzdA=="));
+        assert!(!ApiService::validate_auth("tests", "Basic cdGVzdA=="));
     }
 }
@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::monitor::state::{CheckStatus, StateTracker};
+use crate::monitor::website::Check;
+
+/// Checks that every check depends on (`depends_on`), when one of them is down the failures of the
+/// check are reported as UNREACHABLE.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    parents: HashMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    /// Build the graph of the checks, fails when a check depends on an unknown check or the
+    /// dependencies have a cycle.
+    pub fn new(checks: &[Check]) -> Result<DependencyGraph, String> {
+        let parents: HashMap<String, Vec<String>> = checks.iter()
+            .map(|check| (check.id(), check.settings().depends_on.clone().unwrap_or_default()))
+            .collect();
+        for (check_id, dependencies) in parents.iter() {
+            if let Some(unknown) = dependencies.iter().find(|parent| !parents.contains_key(*parent)) {
+                return Err(format!("{} depends on the unknown check {}", check_id, unknown));
+            }
+        }
+
+        let graph = DependencyGraph { parents };
+        let mut check_ids: Vec<&String> = graph.parents.keys().collect();
+        check_ids.sort();
+        let mut visited = HashSet::new();
+        for check_id in check_ids {
+            graph.find_cycle(check_id, &mut Vec::new(), &mut visited)?;
+        }
+        Ok(graph)
+    }
+
+    /// Depth first search of the dependencies, `path` are the checks being visited.
+    fn find_cycle<'a>(&'a self, check_id: &'a str, path: &mut Vec<&'a str>, visited: &mut HashSet<&'a str>) -> Result<(), String> {
+        if let Some(index) = path.iter().position(|id| *id == check_id) {
+            let mut cycle = path[index..].to_vec();
+            cycle.push(check_id);
+            return Err(format!("dependency cycle: {}", cycle.join(" -> ")));
+        }
+        if visited.contains(check_id) {
+            return Ok(());
+        }
+
+        path.push(check_id);
+        for parent in self.parents(check_id) {
+            self.find_cycle(parent, path, visited)?;
+        }
+        path.pop();
+        visited.insert(check_id);
+        Ok(())
+    }
+
+    pub fn parents(&self, check_id: &str) -> &[String] {
+        self.parents.get(check_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Checks that depend directly on the check, sorted by id.
+    pub fn dependents(&self, check_id: &str) -> Vec<&str> {
+        let mut dependents: Vec<&str> = self.parents.iter()
+            .filter(|(_, parents)| parents.iter().any(|parent| parent == check_id))
+            .map(|(dependent, _)| dependent.as_str())
+            .collect();
+        dependents.sort();
+        dependents
+    }
+
+    /// Length of the longest chain of dependencies of the check, the checks are processed by depth
+    /// so the status of the parents is known before the status of their dependents.
+    pub fn depth(&self, check_id: &str) -> usize {
+        self.parents(check_id).iter()
+            .map(|parent| self.depth(parent) + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// First parent of the check whose last result is DOWN (or UNREACHABLE), its failure doesn't have
+    /// to be confirmed yet, so the dependents aren't alerted while the parent reaches its `fail_threshold`.
+    pub fn failed_parent(&self, check_id: &str, state: &StateTracker) -> Option<&str> {
+        self.parents(check_id).iter()
+            .find(|parent| matches!(
+                state.get(parent).map(|state| state.last),
+                Some(CheckStatus::Down | CheckStatus::Unreachable)
+            ))
+            .map(String::as_str)
+    }
+
+    /// All the checks the check depends on, the nearest first.
+    pub fn ancestors(&self, check_id: &str) -> Vec<&str> {
+        let mut ancestors: Vec<&str> = Vec::new();
+        let mut index = 0;
+        let mut current = check_id;
+        loop {
+            for parent in self.parents(current) {
+                if !ancestors.contains(&parent.as_str()) {
+                    ancestors.push(parent);
+                }
+            }
+            if index >= ancestors.len() {
+                return ancestors;
+            }
+            current = ancestors[index];
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::DependencyGraph;
+    use crate::monitor::state::{CheckStatus, Confirmation, StateTracker};
+    use crate::monitor::website::models::CheckSettings;
+    use crate::monitor::website::tcp::Tcp;
+    use crate::monitor::website::Check;
+
+    fn check(host: &str, depends_on: &[&str]) -> Check {
+        Check::Tcp(Tcp {
+            host: host.to_string(),
+            port: 443,
+            settings: CheckSettings {
+                depends_on: Some(depends_on.iter().map(|id| id.to_string()).collect()),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn invalid_dependencies_test() {
+        let err = DependencyGraph::new(&[check("api", &["TCP db:443"])]).unwrap_err();
+        assert_eq!(err, "TCP api:443 depends on the unknown check TCP db:443");
+
+        let err = DependencyGraph::new(&[
+            check("api", &["TCP db:443"]),
+            check("db", &["TCP network:443"]),
+            check("network", &["TCP api:443"]),
+        ]).unwrap_err();
+        assert_eq!(err, "dependency cycle: TCP api:443 -> TCP db:443 -> TCP network:443 -> TCP api:443");
+
+        assert!(DependencyGraph::new(&[check("api", &["TCP api:443"])]).is_err());
+    }
+
+    #[test]
+    fn dependencies_test() {
+        let graph = DependencyGraph::new(&[
+            check("blog", &["TCP api:443", "TCP cdn:443"]),
+            check("api", &["TCP db:443"]),
+            check("cdn", &[]),
+            check("db", &[]),
+        ]).unwrap();
+
+        assert_eq!(graph.depth("TCP db:443"), 0);
+        assert_eq!(graph.depth("TCP blog:443"), 2);
+        assert_eq!(graph.ancestors("TCP blog:443"), vec!["TCP api:443", "TCP cdn:443", "TCP db:443"]);
+        assert_eq!(graph.dependents("TCP cdn:443"), vec!["TCP blog:443"]);
+        assert!(graph.dependents("TCP blog:443").is_empty());
+
+        let mut state = StateTracker::new();
+        let now = SystemTime::now();
+        state.update("TCP api:443", CheckStatus::Up, now);
+        assert_eq!(graph.failed_parent("TCP blog:443", &state), None);
+        state.update("TCP cdn:443", CheckStatus::Unreachable, now);
+        assert_eq!(graph.failed_parent("TCP blog:443", &state), Some("TCP cdn:443"));
+
+        // the failure of the parent doesn't have to be confirmed
+        state.set_confirmation("TCP db:443", Confirmation { fail: 3, recover: 1 });
+        state.update("TCP db:443", CheckStatus::Down, now);
+        assert_eq!(state.get("TCP db:443").unwrap().status, CheckStatus::Up);
+        assert_eq!(graph.failed_parent("TCP api:443", &state), Some("TCP db:443"));
+        state.update("TCP db:443", CheckStatus::Up, now);
+        assert_eq!(graph.failed_parent("TCP api:443", &state), None);
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::monitor::utils::{format_duration, ToMarkdown};
+
+/// Steps to notify a DOWN alert again while nobody acknowledges it.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EscalationPolicy {
+    pub name: String,
+    pub steps: Vec<EscalationStep>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EscalationStep {
+    /// Minutes since the alert to execute the step.
+    pub after: u64,
+    /// Chats notified in the step, by default the chats of the alert.
+    pub chats: Option<Vec<i64>>,
+}
+
+/// DOWN alert of a check (an incident), open until the check recovers.
+struct OpenAlert {
+    incident: u64,
+    policy: Option<EscalationPolicy>,
+    chats: Vec<i64>,
+    message: String,
+    since: Instant,
+    next_step: usize,
+    /// Sent messages of the alert and its escalations `(chat id, message id)`.
+    messages: Vec<(i64, i64)>,
+    acknowledged_by: Option<String>,
+    /// The alert is notified again at the given moment if the check is still down, its escalation is
+    /// paused in the meantime.
+    muted: Option<Mute>,
+    /// Time the alert was muted, the steps of the escalation are delayed by it.
+    paused: Duration,
+}
+
+#[derive(Clone, Copy)]
+struct Mute {
+    from: Instant,
+    until: Instant,
+}
+
+impl OpenAlert {
+    /// Moment of the next step of the escalation, none once the alert is acknowledged or while it's muted.
+    fn next_due(&self) -> Option<Instant> {
+        if self.acknowledged_by.is_some() || self.muted.is_some() {
+            return None;
+        }
+        self.policy.as_ref()?.steps.get(self.next_step)
+            .map(|step| self.since + self.paused + Duration::from_secs(step.after * 60))
+    }
+
+    /// The alert is routed to the chat, or one of its escalations was sent to it.
+    fn notified_to(&self, chat_id: i64) -> bool {
+        self.chats.contains(&chat_id) || self.messages.iter().any(|(chat, _)| *chat == chat_id)
+    }
+}
+
+/// Notification of a step of the escalation of an alert.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escalation {
+    pub incident: u64,
+    pub check_id: String,
+    pub chats: Vec<i64>,
+    /// Telegram message (markdown format).
+    pub message: String,
+}
+
+/// Alert that was acknowledged or muted, with its messages to update them (their buttons are removed).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedAlert {
+    pub check_id: String,
+    /// Telegram message (markdown format) of the alert.
+    pub message: String,
+    pub messages: Vec<(i64, i64)>,
+}
+
+/// Keeps the DOWN alerts until their checks recover, and the steps of their escalation policies
+/// until they are acknowledged.
+pub struct EscalationTracker {
+    policies: HashMap<String, EscalationPolicy>,
+    alerts: HashMap<String, OpenAlert>,
+    next_incident: u64,
+}
+
+impl EscalationTracker {
+    pub fn new(policies: &[EscalationPolicy]) -> EscalationTracker {
+        let policies = policies.iter()
+            .map(|policy| (policy.name.clone(), policy.clone()))
+            .collect();
+        EscalationTracker { policies, alerts: HashMap::new(), next_incident: 1 }
+    }
+
+    /// Open the alert of a check and start its escalation, an open alert of the check is replaced.
+    /// Returns the id of the incident.
+    pub fn open(&mut self, check_id: &str, policy: Option<&str>, chats: Vec<i64>, message: String, now: Instant) -> u64 {
+        let policy = policy.and_then(|policy| {
+            let found = self.policies.get(policy).cloned();
+            if found.is_none() {
+                println!("Unknown escalation policy {} of {}", policy, check_id);
+            }
+            found
+        });
+        let incident = self.next_incident;
+        self.next_incident += 1;
+        self.alerts.insert(check_id.to_string(), OpenAlert {
+            incident,
+            policy,
+            chats,
+            message,
+            since: now,
+            next_step: 0,
+            messages: Vec::new(),
+            acknowledged_by: None,
+            muted: None,
+            paused: Duration::ZERO,
+        });
+        incident
+    }
+
+    /// Register the sent messages of an incident.
+    pub fn add_messages(&mut self, incident: u64, messages: Vec<(i64, i64)>) {
+        if let Some(alert) = self.alerts.values_mut().find(|alert| alert.incident == incident) {
+            alert.messages.extend(messages);
+        }
+    }
+
+    pub fn is_open(&self, check_id: &str) -> bool {
+        self.alerts.contains_key(check_id)
+    }
+
+    /// Id of the check of an open incident.
+    pub fn check_id(&self, incident: u64) -> Option<String> {
+        self.alerts.iter()
+            .find(|(_, alert)| alert.incident == incident)
+            .map(|(check_id, _)| check_id.clone())
+    }
+
+    /// Stop the escalation of the alert of a check, fails when the alert isn't open or it was
+    /// already acknowledged.
+    pub fn acknowledge(&mut self, check_id: &str, by: &str) -> Result<ClosedAlert, String> {
+        let alert = self.alerts.get_mut(check_id).ok_or("the alert is not open".to_string())?;
+        if let Some(ref acknowledged_by) = alert.acknowledged_by {
+            return Err(format!("the alert was already acknowledged by {}", acknowledged_by));
+        }
+        alert.acknowledged_by = Some(by.to_string());
+        Ok(ClosedAlert { check_id: check_id.to_string(), message: alert.message.clone(), messages: alert.messages.clone() })
+    }
+
+    pub fn acknowledge_incident(&mut self, incident: u64, by: &str) -> Result<ClosedAlert, String> {
+        let check_id = self.check_id(incident).ok_or("the alert is not open".to_string())?;
+        self.acknowledge(&check_id, by)
+    }
+
+    /// Stop the escalation of the alert of a check from a chat, fails when the alert wasn't notified
+    /// to the chat.
+    pub fn acknowledge_in_chat(&mut self, check_id: &str, chat_id: i64, by: &str) -> Result<ClosedAlert, String> {
+        if !self.alerts.get(check_id).is_some_and(|alert| alert.notified_to(chat_id)) {
+            return Err("the alert is not open in this chat".to_string());
+        }
+        self.acknowledge(check_id, by)
+    }
+
+    /// Stop the escalation of all the alerts notified to the chat that weren't acknowledged.
+    pub fn acknowledge_all(&mut self, chat_id: i64, by: &str) -> Vec<ClosedAlert> {
+        let mut check_ids: Vec<String> = self.alerts.iter()
+            .filter(|(_, alert)| alert.notified_to(chat_id))
+            .map(|(check_id, _)| check_id.clone())
+            .collect();
+        check_ids.sort();
+        check_ids.iter()
+            .filter_map(|check_id| self.acknowledge(check_id, by).ok())
+            .collect()
+    }
+
+    /// Pause the escalation of an incident and don't notify its check until the given moment, the
+    /// alert is notified again then if the check is still down and nobody acknowledged it.
+    pub fn mute_incident(&mut self, incident: u64, now: Instant, until: Instant) -> Result<ClosedAlert, String> {
+        let check_id = self.check_id(incident).ok_or("the alert is not open".to_string())?;
+        let alert = self.alerts.get_mut(&check_id).ok_or("the alert is not open".to_string())?;
+        if let Some(ref acknowledged_by) = alert.acknowledged_by {
+            return Err(format!("the alert was already acknowledged by {}", acknowledged_by));
+        }
+        if alert.muted.is_some() {
+            return Err("the alert is already muted".to_string());
+        }
+        alert.muted = Some(Mute { from: now, until });
+        Ok(ClosedAlert { check_id, message: alert.message.clone(), messages: alert.messages.clone() })
+    }
+
+    pub fn is_muted(&self, check_id: &str, now: Instant) -> bool {
+        self.alerts.get(check_id).and_then(|alert| alert.muted).is_some_and(|mute| mute.until > now)
+    }
+
+    /// The check isn't DOWN anymore, the alert is closed.
+    pub fn resolve(&mut self, check_id: &str) {
+        self.alerts.remove(check_id);
+    }
+
+    /// Steps of the escalations that have to be notified, every step is notified once. The alerts whose
+    /// mute expired are notified again (unless they were acknowledged meanwhile), and their escalation
+    /// is resumed.
+    pub fn due(&mut self, now: Instant) -> Vec<Escalation> {
+        let mut escalations = Vec::new();
+        for (check_id, alert) in self.alerts.iter_mut() {
+            if let Some(mute) = alert.muted.filter(|mute| mute.until <= now) {
+                alert.muted = None;
+                alert.paused += mute.until.saturating_duration_since(mute.from);
+                if alert.acknowledged_by.is_some() {
+                    continue;
+                }
+                let header = format!(
+                    "🔔 The mute expired, still down after {}:",
+                    format_duration(now.saturating_duration_since(alert.since)),
+                ).parse_text_to_markdown();
+                escalations.push(Escalation {
+                    incident: alert.incident,
+                    check_id: check_id.clone(),
+                    chats: alert.chats.clone(),
+                    message: format!("{}\n{}", header, alert.message),
+                });
+            }
+            while alert.next_due().is_some_and(|due| due <= now) {
+                let Some(ref policy) = alert.policy else { break };
+                let step = &policy.steps[alert.next_step];
+                alert.next_step += 1;
+                let header = format!(
+                    "🔺 Escalation {}/{}, unacknowledged for {}:",
+                    alert.next_step,
+                    policy.steps.len(),
+                    format_duration(now.saturating_duration_since(alert.since)),
+                ).parse_text_to_markdown();
+                escalations.push(Escalation {
+                    incident: alert.incident,
+                    check_id: check_id.clone(),
+                    chats: step.chats.clone().unwrap_or_else(|| alert.chats.clone()),
+                    message: format!("{}\n{}", header, alert.message),
+                });
+            }
+        }
+        escalations.sort_by(|a, b| a.check_id.cmp(&b.check_id));
+        escalations
+    }
+
+    /// Time remaining until the next step of an escalation or the end of a mute.
+    pub fn time_to_next(&self, now: Instant) -> Option<Duration> {
+        self.alerts.values()
+            .filter_map(|alert| alert.next_due().or(alert.muted.map(|mute| mute.until)))
+            .map(|due| due.saturating_duration_since(now))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{EscalationPolicy, EscalationStep, EscalationTracker};
+
+    fn policy() -> EscalationPolicy {
+        EscalationPolicy {
+            name: "on-call".to_string(),
+            steps: vec![
+                EscalationStep { after: 10, chats: None },
+                EscalationStep { after: 20, chats: Some(vec![-200]) },
+                EscalationStep { after: 30, chats: Some(vec![-300]) },
+            ],
+        }
+    }
+
+    #[test]
+    fn escalation_steps_test() {
+        let mut tracker = EscalationTracker::new(&[policy()]);
+        let start = Instant::now();
+        let minutes = |minutes: u64| start + Duration::from_secs(minutes * 60);
+        let incident = tracker.open("TCP db:5432", Some("on-call"), vec![-100], "❌ TCP db:5432 fails".to_string(), start);
+
+        assert!(tracker.due(minutes(5)).is_empty());
+        assert_eq!(tracker.time_to_next(minutes(5)), Some(Duration::from_secs(5 * 60)));
+
+        let escalations = tracker.due(minutes(10));
+        assert_eq!(escalations.len(), 1);
+        assert_eq!(escalations[0].incident, incident);
+        assert_eq!(escalations[0].chats, vec![-100]);
+        assert_eq!(escalations[0].message, "🔺 Escalation 1/3, unacknowledged for 10m:\n❌ TCP db:5432 fails");
+
+        // the late steps are notified together
+        let escalations = tracker.due(minutes(35));
+        assert_eq!(escalations.iter().map(|e| e.chats.clone()).collect::<Vec<_>>(), vec![vec![-200], vec![-300]]);
+        assert!(tracker.due(minutes(60)).is_empty());
+        assert_eq!(tracker.time_to_next(minutes(60)), None);
+    }
+
+    #[test]
+    fn acknowledgment_stops_the_escalation_test() {
+        let mut tracker = EscalationTracker::new(&[policy()]);
+        let start = Instant::now();
+        let db = tracker.open("TCP db:5432", Some("on-call"), vec![-100], "db".to_string(), start);
+        tracker.open("TCP cache:6379", Some("on-call"), vec![-100], "cache".to_string(), start);
+        tracker.open("TCP queue:5672", None, vec![-100], "queue".to_string(), start);
+        tracker.add_messages(db, vec![(-100, 7)]);
+
+        let acknowledged = tracker.acknowledge_incident(db, "@guille").unwrap();
+        assert_eq!(acknowledged.check_id, "TCP db:5432");
+        assert_eq!(acknowledged.messages, vec![(-100, 7)]);
+        assert_eq!(
+            tracker.acknowledge("TCP db:5432", "@ana"),
+            Err("the alert was already acknowledged by @guille".to_string())
+        );
+        let escalations = tracker.due(start + Duration::from_secs(600));
+        assert_eq!(escalations.len(), 1);
+        assert_eq!(escalations[0].check_id, "TCP cache:6379");
+
+        // only the alerts notified to the chat are acknowledged
+        tracker.open("TCP mail:25", Some("on-call"), vec![-200], "mail".to_string(), start);
+        assert_eq!(
+            tracker.acknowledge_in_chat("TCP mail:25", -100, "@ana"),
+            Err("the alert is not open in this chat".to_string())
+        );
+        let acknowledged = tracker.acknowledge_all(-100, "@ana");
+        assert_eq!(acknowledged.iter().map(|alert| alert.check_id.as_str()).collect::<Vec<_>>(), vec!["TCP cache:6379", "TCP queue:5672"]);
+        assert!(tracker.time_to_next(start).is_some());
+        assert_eq!(tracker.acknowledge_in_chat("TCP mail:25", -200, "@ana").unwrap().check_id, "TCP mail:25");
+        assert_eq!(tracker.time_to_next(start), None);
+
+        tracker.resolve("TCP db:5432");
+        assert!(tracker.acknowledge_incident(db, "@ana").is_err());
+    }
+
+    #[test]
+    fn mute_test() {
+        let mut tracker = EscalationTracker::new(&[policy()]);
+        let start = Instant::now();
+        let minutes = |minutes: u64| start + Duration::from_secs(minutes * 60);
+        let incident = tracker.open("TCP db:5432", Some("on-call"), vec![-100], "db".to_string(), start);
+
+        let muted = tracker.mute_incident(incident, minutes(5), minutes(65)).unwrap();
+        assert_eq!(muted.check_id, "TCP db:5432");
+        assert_eq!(tracker.mute_incident(incident, minutes(6), minutes(66)), Err("the alert is already muted".to_string()));
+        assert!(tracker.is_muted("TCP db:5432", minutes(30)));
+        assert!(tracker.due(minutes(30)).is_empty());
+        assert_eq!(tracker.time_to_next(minutes(30)), Some(Duration::from_secs(35 * 60)));
+
+        // the alert is notified again once the mute expires, and the escalation is resumed
+        let escalations = tracker.due(minutes(65));
+        assert_eq!(escalations.len(), 1);
+        assert_eq!(escalations[0].incident, incident);
+        assert_eq!(escalations[0].chats, vec![-100]);
+        assert_eq!(escalations[0].message, "🔔 The mute expired, still down after 1h 5m:\ndb");
+        assert!(!tracker.is_muted("TCP db:5432", minutes(65)));
+        assert_eq!(tracker.time_to_next(minutes(65)), Some(Duration::from_secs(5 * 60)));
+        assert_eq!(tracker.due(minutes(70))[0].message, "🔺 Escalation 1/3, unacknowledged for 1h 10m:\ndb");
+
+        tracker.resolve("TCP db:5432");
+        assert!(tracker.mute_incident(incident, minutes(70), minutes(130)).is_err());
+    }
+
+    #[test]
+    fn acknowledged_alerts_are_not_notified_after_the_mute_test() {
+        let mut tracker = EscalationTracker::new(&[policy()]);
+        let start = Instant::now();
+        let minutes = |minutes: u64| start + Duration::from_secs(minutes * 60);
+        let incident = tracker.open("TCP db:5432", Some("on-call"), vec![-100], "db".to_string(), start);
+
+        tracker.mute_incident(incident, minutes(5), minutes(65)).unwrap();
+        tracker.acknowledge_incident(incident, "@guille").unwrap();
+        assert!(tracker.due(minutes(65)).is_empty());
+        assert!(!tracker.is_muted("TCP db:5432", minutes(65)));
+        assert_eq!(tracker.time_to_next(minutes(65)), None);
+
+        // an acknowledged alert can't be muted
+        assert_eq!(
+            tracker.mute_incident(incident, minutes(70), minutes(130)),
+            Err("the alert was already acknowledged by @guille".to_string())
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use crate::monitor::state::{CheckStatus, Flapping, Transition};
+use crate::monitor::telegram::models::{InlineKeyboardButton, InlineKeyboardMarkup};
+use crate::monitor::utils::{format_duration, ToMarkdown};
+use crate::monitor::website::models::{CheckKind, CheckResult, FailureReason};
+
+/// Telegram message (markdown format) with the result of a check.
+pub fn result_message(result: &CheckResult) -> String {
+    let error = result.error.clone().unwrap_or_default();
+    let latency = match (result.kind, result.latency, result.reason) {
+        // the latency is already in the error
+        (_, _, Some(FailureReason::Latency)) => "".to_string(),
+        (CheckKind::Api | CheckKind::Frontend, Some(latency), _) => format!(" (latency {} ms)", latency.as_millis()),
+        (CheckKind::Heartbeat, Some(duration), _) => format!(" (last run took {})", format_duration(duration)),
+        _ => "".to_string(),
+    };
+    let perfdata = match result.perfdata {
+        Some(ref perfdata) => {
+            let items: Vec<String> = perfdata.iter()
+                .map(|data| format!("{}={}{}", data.label, data.value, data.unit))
+                .collect();
+            format!(" ({})", items.join(", "))
+        }
+        None => "".to_string(),
+    };
+    let attempts = match result.attempts {
+        Some(attempts) if attempts > 1 => format!(" after {} attempts", attempts),
+        _ => "".to_string(),
+    };
+    if let Some(ref cert) = result.certificate {
+        let validity = format!(
+            "{} days (issuer: {}, SANs: {}, {})",
+            cert.days_remaining,
+            cert.issuer,
+            cert.sans.join(", "),
+            cert.tls_version
+        );
+        return match result.status {
+            CheckStatus::Up => format!("✅ Cert of {} is valid for {}.", result.target, validity),
+            CheckStatus::Degraded => format!("⚠️ Cert of {} expires in {}.", result.target, validity),
+            CheckStatus::Down | CheckStatus::Unreachable => {
+                format!("❌ Error with cert, url: {}. {} ({}).", result.target, error, cert.tls_version)
+            }
+        }.parse_text_to_markdown();
+    }
+    let message = match (result.status, result.kind) {
+        (CheckStatus::Up, CheckKind::Command) if result.output.is_some() => {
+            format!("✅ {} is OK: {}{}", result.check_id, result.output.clone().unwrap_or_default(), perfdata)
+        }
+        (CheckStatus::Up, _) => format!("✅ {} is OK{}{}.", result.check_id, latency, perfdata),
+        (CheckStatus::Down, CheckKind::Certificate) => {
+            format!("❌ Error with cert, url: {}. {}", result.target, error)
+        }
+        (CheckStatus::Down, _) => format!("❌ {} fails: {}{}{}{}.", result.check_id, error, latency, perfdata, attempts),
+        (CheckStatus::Degraded, _) => format!("⚠️ {} is degraded: {}{}{}.", result.check_id, error, latency, perfdata),
+        (CheckStatus::Unreachable, _) => format!("⛔ {} is unreachable: {}.", result.check_id, error),
+    }.parse_text_to_markdown();
+    match result.diff {
+        // inside a code block only the ` and \ characters are escaped
+        Some(ref diff) => format!("{}\n```\n{}\n```", message, diff.replace('\\', "\\\\").replace('`', "\\`")),
+        None => message,
+    }
+}
+
+/// Telegram message (markdown format) notified when a check changes of status.
+pub fn transition_message(transition: &Transition, result: &CheckResult) -> String {
+    match transition.to {
+        CheckStatus::Up => format!(
+            "✅ {} recovered after {}.",
+            transition.check_id,
+            format_duration(transition.duration)
+        ).parse_text_to_markdown(),
+        _ => result_message(result),
+    }
+}
+
+/// Lines (markdown format) appended to the message of a check with the dependents that became
+/// unreachable or recovered at the same time.
+pub fn dependents_message(unreachable: &[String], recovered: &[String]) -> String {
+    let mut message = String::new();
+    if !unreachable.is_empty() {
+        message.push_str(&format!("\n⛔ Unreachable: {}", unreachable.join(", ").parse_text_to_markdown()));
+    }
+    if !recovered.is_empty() {
+        message.push_str(&format!("\n✅ Also recovered: {}", recovered.join(", ").parse_text_to_markdown()));
+    }
+    message
+}
+
+/// Telegram message (markdown format) with the dependents that became unreachable after the alert of
+/// their parent was notified.
+pub fn unreachable_dependents_message(parent: &str, unreachable: &[String]) -> String {
+    format!("⛔ Unreachable because {} is down: {}.", parent, unreachable.join(", ")).parse_text_to_markdown()
+}
+
+/// Telegram message (markdown format) notified when a check starts or stops flapping, the transitions
+/// of the check aren't notified in the meantime.
+pub fn flapping_message(flapping: &Flapping, result: &CheckResult) -> String {
+    match flapping {
+        Flapping::Started(score) => format!(
+            "🔀 {} is flapping ({:.0}% of the last results changed the status), its transitions aren't notified until it's stable.",
+            result.check_id,
+            score
+        ),
+        Flapping::Stopped(_) => {
+            let status = match result.status {
+                CheckStatus::Up => "✅ UP",
+                CheckStatus::Degraded => "⚠️ DEGRADED",
+                CheckStatus::Down => "❌ DOWN",
+                CheckStatus::Unreachable => "⛔ UNREACHABLE",
+            };
+            format!("{} is stable again, it's {}.", result.check_id, status)
+        }
+    }.parse_text_to_markdown()
+}
+
+/// Action of a button of the inline keyboard of a DOWN alert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertAction {
+    Acknowledge,
+    /// Don't notify the alerts of the check for an hour.
+    Mute,
+    /// Execute the check again and reply with its result.
+    Recheck,
+}
+
+impl AlertAction {
+    fn name(&self) -> &'static str {
+        match self {
+            AlertAction::Acknowledge => "ack",
+            AlertAction::Mute => "mute",
+            AlertAction::Recheck => "recheck",
+        }
+    }
+
+    /// Data of the button of the action for an incident, `{action}:{incident}`. The ids of the checks
+    /// aren't used because the data is limited to 64 bytes.
+    pub fn callback_data(&self, incident: u64) -> String {
+        format!("{}:{}", self.name(), incident)
+    }
+
+    pub fn parse_callback_data(data: &str) -> Option<(AlertAction, u64)> {
+        let (name, incident) = data.split_once(':')?;
+        let action = [AlertAction::Acknowledge, AlertAction::Mute, AlertAction::Recheck].into_iter()
+            .find(|action| action.name() == name)?;
+        Some((action, incident.parse().ok()?))
+    }
+}
+
+/// Buttons of the messages of a DOWN alert.
+pub fn alert_keyboard(incident: u64) -> InlineKeyboardMarkup {
+    let button = |text: &str, action: AlertAction| InlineKeyboardButton {
+        text: text.to_string(),
+        callback_data: action.callback_data(incident),
+    };
+    InlineKeyboardMarkup {
+        inline_keyboard: vec![vec![
+            button("✅ Acknowledge", AlertAction::Acknowledge),
+            button("🔕 Mute 1h", AlertAction::Mute),
+            button("🔄 Re-check now", AlertAction::Recheck),
+        ]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        alert_keyboard, dependents_message, flapping_message, result_message, transition_message,
+        unreachable_dependents_message, AlertAction,
+    };
+    use crate::monitor::state::{CheckStatus, Flapping, Transition};
+    use crate::monitor::website::command::PerfData;
+    use crate::monitor::website::models::{CheckKind, CheckResult, FailureReason};
+    use crate::monitor::website::ssl::CertificateInfo;
+
+    #[test]
+    fn result_message_test() {
+        let result = CheckResult::new(
+            "GET https://binarycoffee.dev".to_string(),
+            CheckKind::Frontend,
+            "https://binarycoffee.dev".to_string(),
+        );
+        assert_eq!("✅ GET https://binarycoffee\\.dev is OK\\.", result_message(&result));
+
+        let result = result.fail(FailureReason::Status, "unexpected status 500 Internal Server Error".to_string());
+        assert_eq!(
+            "❌ GET https://binarycoffee\\.dev fails: unexpected status 500 Internal Server Error\\.",
+            result_message(&result)
+        );
+    }
+
+    #[test]
+    fn latency_in_message_test() {
+        let result = CheckResult {
+            latency: Some(Duration::from_millis(9210)),
+            ..CheckResult::new("GET https://binarycoffee.dev".to_string(), CheckKind::Api, "https://binarycoffee.dev".to_string())
+        };
+        assert_eq!(
+            "❌ GET https://binarycoffee\\.dev fails: unexpected status 502 Bad Gateway \\(latency 9210 ms\\)\\.",
+            result_message(&result.clone().fail(FailureReason::Status, "unexpected status 502 Bad Gateway".to_string()))
+        );
+        assert_eq!(
+            "⚠️ GET https://binarycoffee\\.dev is degraded: latency of 9210 ms exceeds the warning threshold of 5000 ms\\.",
+            result_message(&result.degrade(FailureReason::Latency, "latency of 9210 ms exceeds the warning threshold of 5000 ms".to_string()))
+        );
+    }
+
+    #[test]
+    fn perfdata_in_message_test() {
+        let perfdata = |value: f64| Some(vec![PerfData {
+            label: "/".to_string(),
+            value,
+            unit: "MB".to_string(),
+            warn: Some("5948".to_string()),
+            crit: Some("5958".to_string()),
+            min: None,
+            max: None,
+        }]);
+        let result = CheckResult {
+            output: Some("DISK OK".to_string()),
+            perfdata: perfdata(2643.0),
+            ..CheckResult::new("COMMAND disk".to_string(), CheckKind::Command, "check_disk".to_string())
+        };
+        assert_eq!("✅ COMMAND disk is OK: DISK OK \\(/\\=2643MB\\)", result_message(&result));
+
+        let result = CheckResult { perfdata: perfdata(5950.5), ..result };
+        assert_eq!(
+            "⚠️ COMMAND disk is degraded: WARNING: DISK WARNING \\(/\\=5950\\.5MB\\)\\.",
+            result_message(&result.clone().degrade(FailureReason::Command, "WARNING: DISK WARNING".to_string()))
+        );
+        assert_eq!(
+            "❌ COMMAND disk fails: CRITICAL: DISK CRITICAL \\(/\\=5950\\.5MB\\)\\.",
+            result_message(&result.fail(FailureReason::Command, "CRITICAL: DISK CRITICAL".to_string()))
+        );
+    }
+
+    #[test]
+    fn certificate_message_test() {
+        let mut result = CheckResult::new(
+            "SSL binarycoffee.dev".to_string(),
+            CheckKind::Certificate,
+            "binarycoffee.dev".to_string(),
+        );
+        result.certificate = Some(CertificateInfo {
+            days_remaining: 12,
+            issuer: "R3".to_string(),
+            sans: vec!["binarycoffee.dev".to_string()],
+            tls_version: "TLSv1.3".to_string(),
+            warning_threshold: Some(14),
+        });
+        assert_eq!(
+            "✅ Cert of binarycoffee\\.dev is valid for 12 days \\(issuer: R3, SANs: binarycoffee\\.dev, TLSv1\\.3\\)\\.",
+            result_message(&result)
+        );
+        let result = result.degrade(FailureReason::CertificateExpiry, "certificate expires in 12 days".to_string());
+        assert_eq!(
+            "⚠️ Cert of binarycoffee\\.dev expires in 12 days \\(issuer: R3, SANs: binarycoffee\\.dev, TLSv1\\.3\\)\\.",
+            result_message(&result)
+        );
+        let result = result.fail(FailureReason::HostnameMismatch, "certificate is not valid for api.binarycoffee.dev".to_string());
+        assert_eq!(
+            "❌ Error with cert, url: binarycoffee\\.dev\\. certificate is not valid for api\\.binarycoffee\\.dev \\(TLSv1\\.3\\)\\.",
+            result_message(&result)
+        );
+    }
+
+    #[test]
+    fn content_diff_in_message_test() {
+        let result = CheckResult {
+            diff: Some("-<h1>Binary Coffee</h1>\n+<h1>`Hacked`</h1>".to_string()),
+            ..CheckResult::new("GET https://binarycoffee.dev".to_string(), CheckKind::Frontend, "https://binarycoffee.dev".to_string())
+                .fail(FailureReason::ContentChanged, "the content changed".to_string())
+        };
+        assert_eq!(
+            "❌ GET https://binarycoffee\\.dev fails: the content changed\\.\n```\n-<h1>Binary Coffee</h1>\n+<h1>\\`Hacked\\`</h1>\n```",
+            result_message(&result)
+        );
+    }
+
+    #[test]
+    fn transition_message_test() {
+        let result = CheckResult::new(
+            "GET https://binarycoffee.dev".to_string(),
+            CheckKind::Frontend,
+            "https://binarycoffee.dev".to_string(),
+        );
+        let recovered = Transition {
+            check_id: "GET https://binarycoffee.dev".to_string(),
+            from: CheckStatus::Down,
+            to: CheckStatus::Up,
+            duration: Duration::from_secs(14 * 60),
+        };
+        assert_eq!(
+            "✅ GET https://binarycoffee\\.dev recovered after 14m\\.",
+            transition_message(&recovered, &result)
+        );
+
+        let down = Transition { from: CheckStatus::Up, to: CheckStatus::Down, ..recovered };
+        let result = result.fail(FailureReason::Request, "timeout".to_string());
+        assert_eq!(result_message(&result), transition_message(&down, &result));
+    }
+
+    #[test]
+    fn flapping_message_test() {
+        let result = CheckResult::new(
+            "GET https://binarycoffee.dev".to_string(),
+            CheckKind::Frontend,
+            "https://binarycoffee.dev".to_string(),
+        );
+        assert_eq!(
+            "🔀 GET https://binarycoffee\\.dev is flapping \\(32% of the last results changed the status\\), its transitions aren't notified until it's stable\\.",
+            flapping_message(&Flapping::Started(31.6), &result)
+        );
+        assert_eq!(
+            "GET https://binarycoffee\\.dev is stable again, it's ✅ UP\\.",
+            flapping_message(&Flapping::Stopped(5.0), &result)
+        );
+    }
+
+    #[test]
+    fn dependents_message_test() {
+        let result = CheckResult::new(
+            "GET https://binarycoffee.dev".to_string(),
+            CheckKind::Frontend,
+            "https://binarycoffee.dev".to_string(),
+        ).fail(FailureReason::Request, "timeout".to_string());
+        assert_eq!(
+            "⛔ GET https://binarycoffee\\.dev is unreachable: depends on TCP api\\.binarycoffee\\.dev:443, which is down\\.",
+            result_message(&result.unreachable("TCP api.binarycoffee.dev:443"))
+        );
+        assert_eq!(
+            "\n⛔ Unreachable: GET https://binarycoffee\\.dev, GET https://binarycoffee\\.dev/blog",
+            dependents_message(&["GET https://binarycoffee.dev".to_string(), "GET https://binarycoffee.dev/blog".to_string()], &[])
+        );
+        assert_eq!("", dependents_message(&[], &[]));
+        assert_eq!(
+            "⛔ Unreachable because TCP api\\.binarycoffee\\.dev:443 is down: GET https://binarycoffee\\.dev\\.",
+            unreachable_dependents_message("TCP api.binarycoffee.dev:443", &["GET https://binarycoffee.dev".to_string()])
+        );
+    }
+
+    #[test]
+    fn alert_keyboard_test() {
+        let keyboard = alert_keyboard(42);
+        let data: Vec<&str> = keyboard.inline_keyboard[0].iter().map(|button| button.callback_data.as_str()).collect();
+        assert_eq!(data, vec!["ack:42", "mute:42", "recheck:42"]);
+
+        assert_eq!(AlertAction::parse_callback_data("mute:42"), Some((AlertAction::Mute, 42)));
+        assert_eq!(AlertAction::parse_callback_data("recheck:7"), Some((AlertAction::Recheck, 7)));
+        assert_eq!(AlertAction::parse_callback_data("delete:7"), None);
+        assert_eq!(AlertAction::parse_callback_data("ack"), None);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::monitor::website::models::Severity;
+use crate::monitor::website::Check;
+
+/// Rule of the routing table, the alerts of the checks that match all the given matchers are sent
+/// to its chats.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RoutingRule {
+    /// The check has at least one of the tags.
+    pub tags: Option<Vec<String>>,
+    /// The severity of the check is at least this one.
+    pub min_severity: Option<Severity>,
+    pub check_ids: Option<Vec<String>>,
+    /// Telegram chats that receive the alerts.
+    pub chats: Vec<i64>,
+    /// Escalation policy of the alerts, the one of the first matching rule is used.
+    pub escalation: Option<String>,
+}
+
+impl RoutingRule {
+    fn matches(&self, check_id: &str, tags: &[String], severity: Severity) -> bool {
+        let tags_match = self.tags.as_ref().is_none_or(|rule_tags| rule_tags.iter().any(|tag| tags.contains(tag)));
+        let severity_match = self.min_severity.is_none_or(|min_severity| severity >= min_severity);
+        let check_match = self.check_ids.as_ref().is_none_or(|check_ids| check_ids.iter().any(|id| id == check_id));
+        tags_match && severity_match && check_match
+    }
+}
+
+/// Destination chats of the alerts of every check, the alerts of the checks that don't match any rule
+/// are sent to the default `groups`.
+#[derive(Debug, Default)]
+pub struct AlertRouter {
+    routes: HashMap<String, Vec<i64>>,
+    groups: Vec<i64>,
+    escalations: HashMap<String, String>,
+}
+
+impl AlertRouter {
+    pub fn new(rules: &[RoutingRule], checks: &[Check], groups: Vec<i64>) -> AlertRouter {
+        let mut routes = HashMap::new();
+        let mut escalations = HashMap::new();
+        for check in checks {
+            let check_id = check.id();
+            let settings = check.settings();
+            let tags = settings.tags.as_deref().unwrap_or_default();
+            let matching: Vec<&RoutingRule> = rules.iter()
+                .filter(|rule| rule.matches(&check_id, tags, settings.severity()))
+                .collect();
+
+            let escalation = settings.escalation.clone()
+                .or_else(|| matching.iter().find_map(|rule| rule.escalation.clone()));
+            if let Some(escalation) = escalation {
+                escalations.insert(check_id.clone(), escalation);
+            }
+            let chats: Vec<i64> = matching.iter().flat_map(|rule| rule.chats.iter().copied()).collect();
+            if !chats.is_empty() {
+                routes.insert(check_id, chats);
+            }
+        }
+        AlertRouter { routes, groups, escalations }
+    }
+
+    /// Name of the escalation policy of the alerts of the check.
+    pub fn escalation(&self, check_id: &str) -> Option<&str> {
+        self.escalations.get(check_id).map(String::as_str)
+    }
+
+    /// Chats that receive the alerts of the checks, without duplicates.
+    pub fn chats(&self, check_ids: &[&str]) -> Vec<i64> {
+        let mut chats: Vec<i64> = Vec::new();
+        for check_id in check_ids {
+            for chat in self.routes.get(*check_id).unwrap_or(&self.groups) {
+                if !chats.contains(chat) {
+                    chats.push(*chat);
+                }
+            }
+        }
+        chats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlertRouter, RoutingRule};
+    use crate::monitor::website::models::{CheckSettings, Severity};
+    use crate::monitor::website::tcp::Tcp;
+    use crate::monitor::website::Check;
+
+    fn check(host: &str, tags: &[&str], severity: Severity) -> Check {
+        Check::Tcp(Tcp {
+            host: host.to_string(),
+            port: 5432,
+            settings: CheckSettings {
+                tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+                severity: Some(severity),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn alerts_routing_test() {
+        let rules = vec![
+            RoutingRule {
+                tags: Some(vec!["database".to_string()]),
+                chats: vec![-100],
+                escalation: Some("dba".to_string()),
+                ..Default::default()
+            },
+            RoutingRule { min_severity: Some(Severity::Critical), chats: vec![-300], ..Default::default() },
+            RoutingRule { check_ids: Some(vec!["TCP db:5432".to_string()]), chats: vec![-100, -200], ..Default::default() },
+        ];
+        let router = AlertRouter::new(&rules, &[
+            check("db", &["database"], Severity::Warning),
+            check("replica", &["database"], Severity::Critical),
+            check("cache", &["frontend"], Severity::Info),
+        ], vec![-1]);
+
+        assert_eq!(router.chats(&["TCP db:5432"]), vec![-100, -200]);
+        assert_eq!(router.chats(&["TCP replica:5432"]), vec![-100, -300]);
+        assert_eq!(router.chats(&["TCP db:5432", "TCP replica:5432"]), vec![-100, -200, -300]);
+        // default groups
+        assert_eq!(router.chats(&["TCP cache:5432"]), vec![-1]);
+        assert_eq!(router.chats(&["TCP db:5432", "TCP cache:5432"]), vec![-100, -200, -1]);
+
+        assert_eq!(router.escalation("TCP db:5432"), Some("dba"));
+        assert_eq!(router.escalation("TCP cache:5432"), None);
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+struct ScheduledCheck {
+    check_id: String,
+    interval: Duration,
+    next_due: Instant,
+}
+
+/// Keep track of when every check has to be executed again.
+pub struct Scheduler {
+    checks: Vec<ScheduledCheck>,
+}
+
+impl Scheduler {
+    /// Schedule the checks `(id, interval, jitter)`, the first execution of every check is randomly
+    /// delayed up to its jitter.
+    pub fn new(checks: Vec<(String, Duration, Duration)>, now: Instant) -> Scheduler {
+        let mut rng = rand::thread_rng();
+        let checks = checks.into_iter()
+            .map(|(check_id, interval, jitter)| {
+                let delay = if jitter.is_zero() { Duration::ZERO } else { rng.gen_range(Duration::ZERO..=jitter) };
+                ScheduledCheck { check_id, interval, next_due: now + delay }
+            })
+            .collect();
+        Scheduler { checks }
+    }
+
+    /// Ids of the checks that have to be executed, they are re-scheduled after their interval.
+    pub fn due(&mut self, now: Instant) -> Vec<String> {
+        let mut due = Vec::new();
+        for check in self.checks.iter_mut().filter(|check| check.next_due <= now) {
+            due.push(check.check_id.clone());
+            // keep the cadence of the check, unless the monitor is running behind
+            check.next_due += check.interval;
+            if check.next_due < now {
+                check.next_due = now + check.interval;
+            }
+        }
+        due
+    }
+
+    /// Time remaining until the next check is due.
+    pub fn time_to_next(&self, now: Instant) -> Option<Duration> {
+        self.checks.iter()
+            .map(|check| check.next_due.saturating_duration_since(now))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Scheduler;
+
+    #[test]
+    fn checks_are_due_after_their_interval_test() {
+        let start = Instant::now();
+        let mut scheduler = Scheduler::new(vec![
+            ("payments".to_string(), Duration::from_secs(10), Duration::ZERO),
+            ("blog".to_string(), Duration::from_secs(300), Duration::ZERO),
+        ], start);
+
+        assert_eq!(scheduler.due(start), vec!["payments".to_string(), "blog".to_string()]);
+        assert!(scheduler.due(start + Duration::from_secs(5)).is_empty());
+        assert_eq!(scheduler.time_to_next(start + Duration::from_secs(5)), Some(Duration::from_secs(5)));
+        assert_eq!(scheduler.due(start + Duration::from_secs(10)), vec!["payments".to_string()]);
+        assert_eq!(scheduler.due(start + Duration::from_secs(300)), vec!["payments".to_string(), "blog".to_string()]);
+    }
+
+    #[test]
+    fn jitter_delays_the_first_execution_test() {
+        let start = Instant::now();
+        let scheduler = Scheduler::new(vec![
+            ("certs".to_string(), Duration::from_secs(3600), Duration::from_secs(60)),
+        ], start);
+
+        assert!(scheduler.time_to_next(start).unwrap() <= Duration::from_secs(60));
+    }
+}
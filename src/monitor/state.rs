@@ -0,0 +1,319 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CheckStatus {
+    Up,
+    Degraded,
+    Down,
+    /// The check fails because a check it depends on is down.
+    Unreachable,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckState {
+    pub status: CheckStatus,
+    pub since: SystemTime,
+    /// Status of the last result, it can differ from `status` while the change isn't confirmed.
+    pub last: CheckStatus,
+    /// Consecutive results that contradict the status (failures while UP, successes while failing)
+    /// and the moment of the first one.
+    streak: u32,
+    streak_since: SystemTime,
+}
+
+/// Consecutive results needed to confirm a change of status of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confirmation {
+    /// Consecutive failures (DOWN or DEGRADED) before the check is considered failing.
+    pub fail: u32,
+    /// Consecutive successes before a failing check is considered UP.
+    pub recover: u32,
+}
+
+impl Default for Confirmation {
+    fn default() -> Self {
+        Confirmation { fail: 1, recover: 1 }
+    }
+}
+
+/// A change of status of one check, the `duration` is the time the check spent in the `from` status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub check_id: String,
+    pub from: CheckStatus,
+    pub to: CheckStatus,
+    pub duration: Duration,
+}
+
+/// Keeps the last known status of every check, so only the changes of status are notified.
+pub struct StateTracker {
+    states: HashMap<String, CheckState>,
+    confirmations: HashMap<String, Confirmation>,
+}
+
+impl StateTracker {
+    pub fn new() -> StateTracker {
+        StateTracker { states: HashMap::new(), confirmations: HashMap::new() }
+    }
+
+    pub fn get(&self, check_id: &str) -> Option<&CheckState> {
+        self.states.get(check_id)
+    }
+
+    /// Set the consecutive results needed to change the status of a check, by default one.
+    pub fn set_confirmation(&mut self, check_id: &str, confirmation: Confirmation) {
+        self.confirmations.insert(check_id.to_string(), confirmation);
+    }
+
+    /// Register the new status of a check and return the transition if the status changed. Checks
+    /// that were never seen before are considered UP since the moment they are registered.
+    ///
+    /// A failing check is only considered DOWN (or DEGRADED) after `fail` consecutive failures, and
+    /// UP again after `recover` consecutive successes, the transition starts at the first of them.
+    pub fn update(&mut self, check_id: &str, status: CheckStatus, now: SystemTime) -> Option<Transition> {
+        let confirmation = self.confirmations.get(check_id).copied().unwrap_or_default();
+        let state = self.states.entry(check_id.to_string()).or_insert(CheckState {
+            status: CheckStatus::Up,
+            since: now,
+            last: CheckStatus::Up,
+            streak: 0,
+            streak_since: now,
+        });
+        state.last = status;
+
+        let failing = status != CheckStatus::Up;
+        if failing == (state.status != CheckStatus::Up) {
+            // same side, a change between DEGRADED and DOWN doesn't need to be confirmed
+            state.streak = 0;
+            if state.status == status {
+                return None;
+            }
+            state.streak_since = now;
+        } else {
+            if state.streak == 0 {
+                state.streak_since = now;
+            }
+            state.streak += 1;
+            let threshold = if failing { confirmation.fail } else { confirmation.recover };
+            if state.streak < threshold {
+                return None;
+            }
+            state.streak = 0;
+        }
+
+        let transition = Transition {
+            check_id: check_id.to_string(),
+            from: state.status,
+            to: status,
+            duration: state.streak_since.duration_since(state.since).unwrap_or_default(),
+        };
+        state.status = status;
+        state.since = state.streak_since;
+        Some(transition)
+    }
+}
+
+impl Default for StateTracker {
+    fn default() -> Self {
+        StateTracker::new()
+    }
+}
+
+/// Keeps the lowest warning threshold notified for every check, so every threshold is notified once.
+pub struct ThresholdTracker {
+    notified: HashMap<String, u64>,
+}
+
+impl ThresholdTracker {
+    pub fn new() -> ThresholdTracker {
+        ThresholdTracker { notified: HashMap::new() }
+    }
+
+    /// Register the threshold reached by the certificate of a check and return if it wasn't notified
+    /// yet. When the certificate doesn't reach any threshold (it was renewed) the notified thresholds
+    /// are reset.
+    pub fn update(&mut self, check_id: &str, threshold: Option<u64>) -> bool {
+        match threshold {
+            None => {
+                self.notified.remove(check_id);
+                false
+            }
+            Some(threshold) => match self.notified.get(check_id) {
+                Some(notified) if *notified <= threshold => false,
+                _ => {
+                    self.notified.insert(check_id.to_string(), threshold);
+                    true
+                }
+            },
+        }
+    }
+}
+
+impl Default for ThresholdTracker {
+    fn default() -> Self {
+        ThresholdTracker::new()
+    }
+}
+
+/// Start or end of the flapping of a check, with the percentage of changes of status in the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flapping {
+    Started(f64),
+    Stopped(f64),
+}
+
+#[derive(Default)]
+struct FlapState {
+    history: VecDeque<CheckStatus>,
+    flapping: bool,
+}
+
+/// Detects the checks that change of status too often, using the percentage of changes in the last
+/// results. A check starts flapping when the score reaches the high threshold, and stops when it
+/// falls below the low one.
+pub struct FlapDetector {
+    window: usize,
+    high_threshold: f64,
+    low_threshold: f64,
+    checks: HashMap<String, FlapState>,
+}
+
+impl FlapDetector {
+    pub fn new(window: usize, high_threshold: f64, low_threshold: f64) -> FlapDetector {
+        FlapDetector { window: window.max(2), high_threshold, low_threshold, checks: HashMap::new() }
+    }
+
+    pub fn is_flapping(&self, check_id: &str) -> bool {
+        self.checks.get(check_id).is_some_and(|state| state.flapping)
+    }
+
+    /// Register the result of a check and return if it started or stopped flapping. The score is
+    /// relative to the whole window, so a few results can't make a check flap.
+    pub fn update(&mut self, check_id: &str, status: CheckStatus) -> Option<Flapping> {
+        let state = self.checks.entry(check_id.to_string()).or_default();
+        state.history.push_back(status);
+        if state.history.len() > self.window {
+            state.history.pop_front();
+        }
+
+        let changes = state.history.iter().zip(state.history.iter().skip(1))
+            .filter(|(previous, next)| previous != next)
+            .count();
+        let score = changes as f64 * 100.0 / (self.window - 1) as f64;
+
+        if !state.flapping && score >= self.high_threshold {
+            state.flapping = true;
+            Some(Flapping::Started(score))
+        } else if state.flapping && score < self.low_threshold {
+            state.flapping = false;
+            Some(Flapping::Stopped(score))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{CheckStatus, Confirmation, FlapDetector, Flapping, StateTracker, ThresholdTracker};
+
+    #[test]
+    fn only_transitions_are_reported_test() {
+        let mut tracker = StateTracker::new();
+        let start = SystemTime::now();
+
+        assert!(tracker.update("api", CheckStatus::Up, start).is_none());
+        assert!(tracker.update("api", CheckStatus::Up, start + Duration::from_secs(20)).is_none());
+
+        let down = tracker.update("api", CheckStatus::Down, start + Duration::from_secs(40)).unwrap();
+        assert_eq!(down.from, CheckStatus::Up);
+        assert_eq!(down.to, CheckStatus::Down);
+        assert!(tracker.update("api", CheckStatus::Down, start + Duration::from_secs(60)).is_none());
+
+        let up = tracker.update("api", CheckStatus::Up, start + Duration::from_secs(40 + 14 * 60)).unwrap();
+        assert_eq!(up.from, CheckStatus::Down);
+        assert_eq!(up.to, CheckStatus::Up);
+        assert_eq!(up.duration, Duration::from_secs(14 * 60));
+    }
+
+    #[test]
+    fn unknown_check_starts_up_test() {
+        let mut tracker = StateTracker::new();
+        let now = SystemTime::now();
+
+        let transition = tracker.update("frontend", CheckStatus::Degraded, now).unwrap();
+        assert_eq!(transition.from, CheckStatus::Up);
+        assert_eq!(transition.duration, Duration::from_secs(0));
+        assert_eq!(tracker.get("frontend").unwrap().status, CheckStatus::Degraded);
+    }
+
+    #[test]
+    fn transitions_are_confirmed_test() {
+        let mut tracker = StateTracker::new();
+        tracker.set_confirmation("api", Confirmation { fail: 3, recover: 2 });
+        let start = SystemTime::now();
+        let secs = |secs: u64| start + Duration::from_secs(secs);
+
+        assert!(tracker.update("api", CheckStatus::Up, secs(0)).is_none());
+        // a blip is not reported
+        assert!(tracker.update("api", CheckStatus::Down, secs(10)).is_none());
+        assert!(tracker.update("api", CheckStatus::Up, secs(20)).is_none());
+
+        assert!(tracker.update("api", CheckStatus::Down, secs(30)).is_none());
+        assert!(tracker.update("api", CheckStatus::Degraded, secs(40)).is_none());
+        let down = tracker.update("api", CheckStatus::Down, secs(50)).unwrap();
+        assert_eq!(down.to, CheckStatus::Down);
+        assert_eq!(down.duration, Duration::from_secs(30));
+        assert_eq!(tracker.get("api").unwrap().since, secs(30));
+
+        // changes between failing statuses are not confirmed
+        assert_eq!(tracker.update("api", CheckStatus::Degraded, secs(60)).unwrap().to, CheckStatus::Degraded);
+
+        assert!(tracker.update("api", CheckStatus::Up, secs(70)).is_none());
+        assert!(tracker.update("api", CheckStatus::Degraded, secs(80)).is_none());
+        assert!(tracker.update("api", CheckStatus::Up, secs(90)).is_none());
+        let up = tracker.update("api", CheckStatus::Up, secs(100)).unwrap();
+        assert_eq!(up.from, CheckStatus::Degraded);
+        assert_eq!(up.duration, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn every_threshold_is_notified_once_test() {
+        let mut tracker = ThresholdTracker::new();
+
+        assert!(!tracker.update("certs", None));
+        assert!(tracker.update("certs", Some(30)));
+        assert!(!tracker.update("certs", Some(30)));
+        assert!(tracker.update("certs", Some(14)));
+        assert!(!tracker.update("certs", Some(14)));
+        // renewed certificate
+        assert!(!tracker.update("certs", None));
+        assert!(tracker.update("certs", Some(30)));
+    }
+
+    #[test]
+    fn flapping_test() {
+        let mut detector = FlapDetector::new(11, 30.0, 10.0);
+
+        // 2 changes in the first results aren't enough
+        assert_eq!(detector.update("api", CheckStatus::Up), None);
+        assert_eq!(detector.update("api", CheckStatus::Down), None);
+        assert_eq!(detector.update("api", CheckStatus::Up), None);
+        assert_eq!(detector.update("api", CheckStatus::Down), Some(Flapping::Started(30.0)));
+        assert!(detector.is_flapping("api"));
+        assert!(!detector.is_flapping("blog"));
+
+        // still flapping while the changes are in the window
+        for _ in 0..9 {
+            assert_eq!(detector.update("api", CheckStatus::Down), None);
+        }
+        assert_eq!(detector.update("api", CheckStatus::Down), Some(Flapping::Stopped(0.0)));
+        assert!(!detector.is_flapping("api"));
+    }
+}
@@ -2,7 +2,10 @@ use async_trait::async_trait;
 use mockall::automock;
 
 use crate::config::Config;
-use crate::monitor::telegram::models::{GetMyCommandsRes, GetUpdatesRes, SendMessageBody, Update};
+use crate::monitor::telegram::models::{
+    AnswerCallbackQueryBody, EditMessageTextBody, GetMyCommandsRes, GetUpdatesRes, InlineKeyboardMarkup,
+    SendMessageBody, SendMessageRes, Update,
+};
 
 use self::models::{BotCommand, SetMyCommandsBody};
 
@@ -33,6 +36,12 @@ pub trait TelegramServiceTrait {
     async fn get_all_updates(&mut self) -> Vec<Update>;
     async fn send_pendings_messages(&mut self);
     async fn send_message(&mut self, text: String, groups: &Option<Vec<i64>>);
+    /// Send a message with buttons, returns the ids of the sent messages `(chat id, message id)`.
+    async fn send_message_with_keyboard(&mut self, text: String, groups: &Option<Vec<i64>>, keyboard: InlineKeyboardMarkup) -> Vec<(i64, i64)>;
+    /// Replace the text of a sent message and remove its buttons.
+    async fn edit_message(&mut self, chat_id: i64, message_id: i64, text: String);
+    /// Confirm the press of a button, the text is shown to the user.
+    async fn answer_callback_query(&mut self, callback_query_id: String, text: String);
     async fn sync_commands(&mut self);
     async fn set_commands(&mut self, commands: Vec<BotCommand>);
     async fn get_commands(&mut self) -> Vec<BotCommand>;
@@ -40,10 +49,10 @@ pub trait TelegramServiceTrait {
 
 impl TelegramService {
     pub fn new(configs: Config) -> TelegramService {
-        let api_url = String::from(format!(
+        let api_url = format!(
             "https://api.telegram.org/bot{}",
             configs.telegram_bot_token.clone().unwrap()
-        ));
+        );
         TelegramService {
             configs: configs.clone(),
             api_url,
@@ -52,25 +61,22 @@ impl TelegramService {
     }
 
     async fn get_updates(&mut self, limit: usize, offset: usize) -> Option<GetUpdatesRes> {
-        let route = String::from(format!(
-            "{}/getUpdates?allowed_updates=[\"message\"]&limit={}&offset={}",
+        let route = format!(
+            "{}/getUpdates?allowed_updates=[\"message\",\"callback_query\"]&limit={}&offset={}",
             self.api_url, limit, offset
-        ));
+        );
         let res_value = self
             .retry_request(&TelegramRequest::Get { url: route })
             .await;
         if let Ok(res) = res_value {
-            match res.status() {
-                reqwest::StatusCode::OK => {
-                    let res_text = res.text().await.unwrap();
-                    let res_json: GetUpdatesRes = serde_json::from_str(&res_text)
-                        .expect("Error deserializing configuration json file.");
-                    return Some(res_json);
-                }
-                _ => {}
+            if res.status() == reqwest::StatusCode::OK {
+                let res_text = res.text().await.unwrap();
+                let res_json: GetUpdatesRes = serde_json::from_str(&res_text)
+                    .expect("Error deserializing configuration json file.");
+                return Some(res_json);
             }
         }
-        return None;
+        None
     }
 
     async fn retry_request(&mut self, req: &TelegramRequest) -> Result<reqwest::Response, String> {
@@ -136,17 +142,63 @@ impl TelegramService {
     ) -> Result<reqwest::Response, reqwest::Error> {
         let client = reqwest::Client::new();
 
-        return client
+        client
             .post(url.clone())
             .header("Content-Type", content_type)
             .body(body.clone())
             .send()
-            .await;
+            .await
+    }
+
+    /// Send the message to the groups (by default the configured ones), returns the ids of the sent
+    /// messages `(chat id, message id)`.
+    async fn send(&mut self, text: String, groups: &Option<Vec<i64>>, keyboard: Option<InlineKeyboardMarkup>) -> Vec<(i64, i64)> {
+        let groups_ids = if let Some(ids) = groups {
+            ids.clone()
+        } else {
+            self.configs.groups.clone().unwrap()
+        };
+        let route = format!("{}/sendMessage", self.api_url);
+        println!("route: {}", route);
+        let mut sent = Vec::new();
+        for chat_id in groups_ids {
+            let body_obj = SendMessageBody {
+                chat_id,
+                text: text.clone(),
+                parse_mode: "MarkdownV2".to_string(),
+                reply_markup: keyboard.clone(),
+            };
+            let body = serde_json::to_string(&body_obj).expect("Error serializing body.");
+            println!("body: {}", body);
+            let res_value = self
+                .retry_request(&TelegramRequest::Post {
+                    url: route.clone(),
+                    body,
+                    content_type: String::from("application/json"),
+                })
+                .await;
+            if let Ok(res) = res_value {
+                match res.status() {
+                    reqwest::StatusCode::OK => {
+                        if let Ok(res) = res.json::<SendMessageRes>().await {
+                            sent.push((chat_id, res.result.message_id));
+                        }
+                    }
+                    _ => {
+                        println!(
+                            "Error to send message to group: {}. res: {:?}",
+                            chat_id, res
+                        );
+                    }
+                }
+            }
+        }
+        sent
     }
 
     async fn get_request(&self, url: String) -> Result<reqwest::Response, reqwest::Error> {
         let client = reqwest::Client::new();
-        return client.get(url.to_owned()).send().await;
+        client.get(url.to_owned()).send().await
     }
 }
 
@@ -179,47 +231,54 @@ impl TelegramServiceTrait for TelegramService {
             pendins.push(self.pending_messages.remove(0));
         }
         for req in pendins.iter() {
-            if let Ok(_) = self.retry_request(req).await {}
+            let _ = self.retry_request(req).await;
         }
     }
 
     async fn send_message(&mut self, text: String, groups: &Option<Vec<i64>>) {
-        let groups_ids = if let Some(ids) = groups {
-            ids.clone()
-        } else {
-            self.configs.groups.clone().unwrap()
+        self.send(text, groups, None).await;
+    }
+
+    async fn send_message_with_keyboard(&mut self, text: String, groups: &Option<Vec<i64>>, keyboard: InlineKeyboardMarkup) -> Vec<(i64, i64)> {
+        self.send(text, groups, Some(keyboard)).await
+    }
+
+    async fn edit_message(&mut self, chat_id: i64, message_id: i64, text: String) {
+        let route = format!("{}/editMessageText", self.api_url);
+        let body_obj = EditMessageTextBody {
+            chat_id,
+            message_id,
+            text,
+            parse_mode: "MarkdownV2".to_string(),
         };
-        let route = String::from(format!("{}/sendMessage", self.api_url));
-        println!("route: {}", route);
-        for chat_id in groups_ids {
-            let body_obj = SendMessageBody {
-                chat_id,
-                text: text.clone(),
-                parse_mode: "MarkdownV2".to_string(),
-            };
-            let body = serde_json::to_string(&body_obj).expect("Error serializing body.");
-            println!("body: {}", body);
-            let res_value = self
-                .retry_request(&TelegramRequest::Post {
-                    url: route.clone(),
-                    body,
-                    content_type: String::from("application/json"),
-                })
-                .await;
-            if let Ok(res) = res_value {
-                match res.status() {
-                    reqwest::StatusCode::OK => {}
-                    _ => {
-                        println!(
-                            "Error to send message to group: {}. res: {:?}",
-                            chat_id, res
-                        );
-                    }
-                }
+        let body = serde_json::to_string(&body_obj).expect("Error serializing body.");
+        let res_value = self
+            .retry_request(&TelegramRequest::Post {
+                url: route,
+                body,
+                content_type: String::from("application/json"),
+            })
+            .await;
+        if let Ok(res) = res_value {
+            if res.status() != reqwest::StatusCode::OK {
+                println!("Error editing the message {} of group: {}. res: {:?}", message_id, chat_id, res);
             }
         }
     }
 
+    async fn answer_callback_query(&mut self, callback_query_id: String, text: String) {
+        let route = format!("{}/answerCallbackQuery", self.api_url);
+        let body_obj = AnswerCallbackQueryBody { callback_query_id, text };
+        let body = serde_json::to_string(&body_obj).expect("Error serializing body.");
+        let _ = self
+            .retry_request(&TelegramRequest::Post {
+                url: route,
+                body,
+                content_type: String::from("application/json"),
+            })
+            .await;
+    }
+
     async fn sync_commands(&mut self) {
         let commands = vec![
             BotCommand {
@@ -238,6 +297,42 @@ impl TelegramServiceTrait for TelegramService {
                 command: "/check_certs".to_string(),
                 description: "Validate certificates.".to_string(),
             },
+            BotCommand {
+                command: "/check_tcp".to_string(),
+                description: "Validate tcp services.".to_string(),
+            },
+            BotCommand {
+                command: "/check_dns".to_string(),
+                description: "Validate dns records.".to_string(),
+            },
+            BotCommand {
+                command: "/check_scenarios".to_string(),
+                description: "Validate multi-step scenarios.".to_string(),
+            },
+            BotCommand {
+                command: "/check_sitemaps".to_string(),
+                description: "Validate the pages of the sitemaps.".to_string(),
+            },
+            BotCommand {
+                command: "/check_links".to_string(),
+                description: "Validate the links of the frontend pages.".to_string(),
+            },
+            BotCommand {
+                command: "/check_heartbeats".to_string(),
+                description: "Validate the heartbeats of the jobs.".to_string(),
+            },
+            BotCommand {
+                command: "/check_commands".to_string(),
+                description: "Execute the command checks.".to_string(),
+            },
+            BotCommand {
+                command: "/ack".to_string(),
+                description: "Acknowledge the alerts of this chat to stop their escalation.".to_string(),
+            },
+            BotCommand {
+                command: "/accept_content".to_string(),
+                description: "Accept the content change of a check as its new baseline.".to_string(),
+            },
             BotCommand {
                 command: "/pause".to_string(),
                 description: "Pause validations.".to_string(),
@@ -251,7 +346,7 @@ impl TelegramServiceTrait for TelegramService {
     }
 
     async fn set_commands(&mut self, commands: Vec<BotCommand>) {
-        let route = String::from(format!("{}/setMyCommands", self.api_url));
+        let route = format!("{}/setMyCommands", self.api_url);
         let body_obj = SetMyCommandsBody { commands };
         let body = serde_json::to_string(&body_obj).expect("todo");
         let res_value = self
@@ -272,19 +367,16 @@ impl TelegramServiceTrait for TelegramService {
     }
 
     async fn get_commands(&mut self) -> Vec<BotCommand> {
-        let route = String::from(format!("{}/getMyCommands", self.api_url));
+        let route = format!("{}/getMyCommands", self.api_url);
         let res = self
             .retry_request(&TelegramRequest::Get { url: route })
             .await;
         if let Ok(bot_res) = res {
-            match bot_res.status() {
-                reqwest::StatusCode::OK => {
-                    let bot_commands_res = bot_res.text().await.unwrap();
-                    let bot_commands: GetMyCommandsRes = serde_json::from_str(&bot_commands_res)
-                        .expect("Error deserializing json response from string.");
-                    return bot_commands.result;
-                }
-                _ => {}
+            if bot_res.status() == reqwest::StatusCode::OK {
+                let bot_commands_res = bot_res.text().await.unwrap();
+                let bot_commands: GetMyCommandsRes = serde_json::from_str(&bot_commands_res)
+                    .expect("Error deserializing json response from string.");
+                return bot_commands.result;
             }
         }
         return Vec::new();
@@ -9,10 +9,14 @@ pub struct GetUpdatesRes {
 pub struct Update {
     pub update_id: i64,
     pub message: Option<Message>,
+    /// Press of a button of the inline keyboard of a message.
+    pub callback_query: Option<CallbackQuery>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Message {
+    pub message_id: i64,
+    pub from: Option<User>,
     pub chat: Chat,
     pub text: Option<String>,
     pub entities: Option<Vec<MessageEntity>>,
@@ -23,6 +27,31 @@ pub struct Chat {
     pub id: i64,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: i64,
+    pub first_name: String,
+    pub username: Option<String>,
+}
+
+impl User {
+    /// Name of the user shown in the messages, the username when it has one.
+    pub fn display_name(&self) -> String {
+        match self.username {
+            Some(ref username) => format!("@{}", username),
+            None => self.first_name.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: User,
+    pub message: Option<Message>,
+    pub data: Option<String>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct MessageEntity {
     #[serde(rename = "type")]
@@ -36,6 +65,39 @@ pub struct SendMessageBody {
     pub chat_id: i64,
     pub text: String,
     pub parse_mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_markup: Option<InlineKeyboardMarkup>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SendMessageRes {
+    pub result: Message,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct InlineKeyboardMarkup {
+    pub inline_keyboard: Vec<Vec<InlineKeyboardButton>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct InlineKeyboardButton {
+    pub text: String,
+    pub callback_data: String,
+}
+
+/// Replace the text of a message, the inline keyboard is removed.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EditMessageTextBody {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub text: String,
+    pub parse_mode: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnswerCallbackQueryBody {
+    pub callback_query_id: String,
+    pub text: String,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
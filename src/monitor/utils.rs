@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub trait ToMarkdown {
     fn parse_text_to_markdown(&self) -> String;
 }
@@ -31,3 +33,33 @@ impl ToMarkdown for String {
         new_text
     }
 }
+
+/// Human readable representation of a duration, ex: `2h 5m`, `14m`, `45s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) = (secs / 86400, (secs % 86400) / 3600, (secs % 3600) / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::format_duration;
+
+    #[test]
+    fn format_duration_test() {
+        assert_eq!("45s", format_duration(Duration::from_secs(45)));
+        assert_eq!("14m", format_duration(Duration::from_secs(14 * 60 + 10)));
+        assert_eq!("2h 5m", format_duration(Duration::from_secs(2 * 3600 + 5 * 60)));
+        assert_eq!("1d 3h", format_duration(Duration::from_secs(86400 + 3 * 3600)));
+    }
+}
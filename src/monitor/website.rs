@@ -1,22 +1,89 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use serde::{Deserialize, Serialize};
 
-use checkssl::CheckSSL;
-use reqwest::Client;
+use futures_util::future::join_all;
+use tokio::time::sleep;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
+use reqwest::header::HeaderMap;
 
 use crate::config::Config;
-use crate::monitor::utils::ToMarkdown;
+use crate::monitor::website::assertions::ResponseAssertions;
+use crate::monitor::website::auth::{resolve_secret, Auth};
+use crate::monitor::website::limiter::ConcurrencyLimiter;
+use crate::monitor::website::command::{check_command, Command};
+use crate::monitor::website::content::{ContentChange, ContentTracker};
+use crate::monitor::website::crawler::{check_crawl, Crawl, CrawlCheck};
+use crate::monitor::website::dns::{check_dns, Dns};
+use crate::monitor::website::heartbeat::{Heartbeat, HeartbeatRegistry};
+use crate::monitor::website::retry::{RetryPolicy, DEFAULT_REQUEST_TIMEOUT};
+use crate::monitor::website::scenario::{Scenario, Step};
+use crate::monitor::website::sitemap::{check_sitemap, Sitemap};
+use crate::monitor::website::ssl::{check_certificate, Ssl};
+use crate::monitor::website::tcp::{check_tcp, Tcp};
+use crate::monitor::state::CheckStatus;
+use crate::monitor::website::models::{CheckKind, CheckResult, CheckSettings, FailureReason, Timings};
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+pub mod assertions;
+pub mod auth;
+pub mod command;
+pub mod content;
+pub mod crawler;
+pub mod dns;
+pub mod heartbeat;
+pub mod limiter;
+pub mod models;
+pub mod retry;
+pub mod scenario;
+pub mod sitemap;
+pub mod ssl;
+pub mod tcp;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct Post {
     pub url: String,
     pub body: String,
     pub content_type: String,
+    pub content_change: Option<ContentChange>,
+    #[serde(flatten)]
+    pub retry: RetryPolicy,
+    #[serde(flatten)]
+    pub assertions: ResponseAssertions,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct Get {
     pub url: String,
+    /// Validate the links and assets of the page, only for frontend tests.
+    pub crawl: Option<Crawl>,
+    pub content_change: Option<ContentChange>,
+    #[serde(flatten)]
+    pub retry: RetryPolicy,
+    #[serde(flatten)]
+    pub assertions: ResponseAssertions,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+/// Http test with any method, headers and authentication.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Http {
+    pub url: String,
+    pub method: String,
+    /// Headers of the request, the values can reference environment variables (`${VAR_NAME}`).
+    pub headers: Option<HashMap<String, String>>,
+    pub body: Option<String>,
+    pub auth: Option<Auth>,
+    pub content_change: Option<ContentChange>,
+    #[serde(flatten)]
+    pub retry: RetryPolicy,
+    #[serde(flatten)]
+    pub assertions: ResponseAssertions,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -24,155 +91,563 @@ pub struct Get {
 pub enum RouteTest {
     POST(Post),
     GET(Get),
+    HTTP(Http),
+}
+
+impl RouteTest {
+    /// Identifier of the check, used to keep track of its status between executions.
+    pub fn id(&self) -> String {
+        match self {
+            RouteTest::POST(post) => format!("POST {}", post.url),
+            RouteTest::GET(get) => format!("GET {}", get.url),
+            RouteTest::HTTP(http) => format!("{} {}", http.method.to_uppercase(), http.url),
+        }
+    }
+
+    pub fn retry(&self) -> &RetryPolicy {
+        match self {
+            RouteTest::POST(post) => &post.retry,
+            RouteTest::GET(get) => &get.retry,
+            RouteTest::HTTP(http) => &http.retry,
+        }
+    }
+
+    /// Request of the test, the secrets of the headers and the authentication are resolved.
+    pub fn request(&self, client: &Client) -> Result<RequestBuilder, String> {
+        match self {
+            RouteTest::POST(post) => Ok(client.post(&post.url)
+                .header("Content-Type", &post.content_type)
+                .body(post.body.to_owned())),
+            RouteTest::GET(get) => Ok(client.get(&get.url)),
+            RouteTest::HTTP(http) => http_request(
+                client, &http.method, &http.url, http.headers.as_ref(), http.body.as_ref(), http.auth.as_ref(),
+            ),
+        }
+    }
+
+    /// Http method of the request, in uppercase.
+    pub fn method(&self) -> String {
+        match self {
+            RouteTest::POST(_) => "POST".to_string(),
+            RouteTest::GET(_) => "GET".to_string(),
+            RouteTest::HTTP(http) => http.method.to_uppercase(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        match self {
+            RouteTest::POST(post) => &post.url,
+            RouteTest::GET(get) => &get.url,
+            RouteTest::HTTP(http) => &http.url,
+        }
+    }
+
+    pub fn settings(&self) -> &CheckSettings {
+        match self {
+            RouteTest::POST(post) => &post.settings,
+            RouteTest::GET(get) => &get.settings,
+            RouteTest::HTTP(http) => &http.settings,
+        }
+    }
+
+    pub fn assertions(&self) -> &ResponseAssertions {
+        match self {
+            RouteTest::POST(post) => &post.assertions,
+            RouteTest::GET(get) => &get.assertions,
+            RouteTest::HTTP(http) => &http.assertions,
+        }
+    }
+
+    /// Settings to detect the changes of the body of the response.
+    pub fn content_change(&self) -> Option<&ContentChange> {
+        match self {
+            RouteTest::POST(post) => post.content_change.as_ref(),
+            RouteTest::GET(get) => get.content_change.as_ref(),
+            RouteTest::HTTP(http) => http.content_change.as_ref(),
+        }
+    }
+}
+
+/// Request with any method, headers and authentication, the secrets of the headers and the
+/// authentication are resolved. Used by the http tests and the steps of the scenarios.
+fn http_request(
+    client: &Client,
+    method: &str,
+    url: &str,
+    headers: Option<&HashMap<String, String>>,
+    body: Option<&String>,
+    auth: Option<&Auth>,
+) -> Result<RequestBuilder, String> {
+    let method = Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| format!("invalid http method {}", method))?;
+    let mut request = client.request(method, url);
+    for (name, value) in headers.into_iter().flatten() {
+        request = request.header(name, resolve_secret(value)?);
+    }
+    if let Some(body) = body {
+        request = request.body(body.to_owned());
+    }
+    match auth {
+        Some(auth) => auth.apply(request),
+        None => Ok(request),
+    }
+}
+
+/// A configured check, with the group of tests where it was defined.
+#[derive(Debug, Clone)]
+pub enum Check {
+    Route(CheckKind, Box<RouteTest>),
+    Certificate(Ssl),
+    Tcp(Tcp),
+    Dns(Dns),
+    Scenario(Scenario),
+    Sitemap(Sitemap),
+    Crawl(CrawlCheck),
+    Heartbeat(Heartbeat),
+    Command(Command),
+}
+
+impl Check {
+    pub fn id(&self) -> String {
+        match self {
+            Check::Route(_, test) => test.id(),
+            Check::Certificate(ssl) => ssl.id(),
+            Check::Tcp(tcp) => tcp.id(),
+            Check::Dns(dns) => dns.id(),
+            Check::Scenario(scenario) => scenario.id(),
+            Check::Sitemap(sitemap) => sitemap.id(),
+            Check::Crawl(crawl) => crawl.id(),
+            Check::Heartbeat(heartbeat) => heartbeat.id(),
+            Check::Command(command) => command.id(),
+        }
+    }
+
+    pub fn kind(&self) -> CheckKind {
+        match self {
+            Check::Route(kind, _) => *kind,
+            Check::Certificate(_) => CheckKind::Certificate,
+            Check::Tcp(_) => CheckKind::Tcp,
+            Check::Dns(_) => CheckKind::Dns,
+            Check::Scenario(_) => CheckKind::Scenario,
+            Check::Sitemap(_) => CheckKind::Sitemap,
+            Check::Crawl(_) => CheckKind::Crawl,
+            Check::Heartbeat(_) => CheckKind::Heartbeat,
+            Check::Command(_) => CheckKind::Command,
+        }
+    }
+
+    pub fn settings(&self) -> &CheckSettings {
+        match self {
+            Check::Route(_, test) => test.settings(),
+            Check::Certificate(ssl) => &ssl.settings,
+            Check::Tcp(tcp) => &tcp.settings,
+            Check::Dns(dns) => &dns.settings,
+            Check::Scenario(scenario) => &scenario.settings,
+            Check::Sitemap(sitemap) => &sitemap.settings,
+            Check::Crawl(crawl) => &crawl.settings,
+            Check::Heartbeat(heartbeat) => &heartbeat.settings,
+            Check::Command(command) => &command.settings,
+        }
+    }
+}
+
+/// List of all the checks of the configurations.
+pub fn configured_checks(configs: &Config) -> Vec<Check> {
+    let api = configs.api_tests.iter().flatten()
+        .map(|test| Check::Route(CheckKind::Api, Box::new(test.clone())));
+    let frontend = configs.frontend_tests.iter().flatten()
+        .map(|test| Check::Route(CheckKind::Frontend, Box::new(test.clone())));
+    let certificates = configs.ssl_tests.iter().flatten()
+        .map(|ssl| Check::Certificate(ssl.clone()));
+    let tcp = configs.tcp_tests.iter().flatten()
+        .map(|tcp| Check::Tcp(tcp.clone()));
+    let dns = configs.dns_tests.iter().flatten()
+        .map(|dns| Check::Dns(dns.clone()));
+    let scenarios = configs.scenario_tests.iter().flatten()
+        .map(|scenario| Check::Scenario(scenario.clone()));
+    let sitemaps = configs.sitemap_tests.iter().flatten()
+        .map(|sitemap| Check::Sitemap(sitemap.clone()));
+    let crawls = configs.frontend_tests.iter().flatten()
+        .filter_map(|test| match test {
+            RouteTest::GET(Get { url, crawl: Some(crawl), .. }) => Some(Check::Crawl(CrawlCheck::new(url, crawl))),
+            _ => None,
+        });
+    let heartbeats = configs.heartbeats.iter().flatten()
+        .map(|heartbeat| Check::Heartbeat(heartbeat.clone()));
+    let commands = configs.command_tests.iter().flatten()
+        .map(|command| Check::Command(command.clone()));
+    api.chain(frontend).chain(certificates).chain(tcp).chain(dns).chain(scenarios).chain(sitemaps).chain(crawls)
+        .chain(heartbeats).chain(commands)
+        .collect()
 }
 
+/// Executes the checks, the clones of the service share the limiter, the content baselines and the
+/// heartbeats, so the checks can run without locking the service.
+#[derive(Clone)]
 pub struct WebsiteService {
     configs: Config,
+    limiter: Arc<ConcurrencyLimiter>,
+    content: Arc<ContentTracker>,
+    heartbeats: HeartbeatRegistry,
 }
 
 impl WebsiteService {
     pub fn new(configs: Config) -> WebsiteService {
-        WebsiteService { configs }
+        let limiter = ConcurrencyLimiter::new(
+            configs.max_concurrent_checks.unwrap(),
+            configs.max_concurrent_checks_per_host.unwrap(),
+        );
+        let heartbeats = HeartbeatRegistry::new(
+            configs.heartbeats.as_deref().unwrap_or_default(),
+            SystemTime::now(),
+        );
+        WebsiteService {
+            configs,
+            limiter: Arc::new(limiter),
+            content: Arc::new(ContentTracker::new()),
+            heartbeats,
+        }
     }
 
-    pub async fn summary(&self) -> Vec<String> {
-        let mut errors = Vec::new();
+    /// Registry of the pings of the heartbeats, shared with the API that receives them.
+    pub fn heartbeats(&self) -> HeartbeatRegistry {
+        self.heartbeats.clone()
+    }
 
-        errors.append(&mut self.api_vitally().await);
-        errors.append(&mut self.frontend_vitaly().await);
-        errors.append(&mut self.certificates_vitaly().await);
+    /// List of all the configured checks.
+    pub fn checks(&self) -> Vec<Check> {
+        configured_checks(&self.configs)
+    }
 
-        errors
+    /// Accept the change of the content of a check, the body of its next scheduled run becomes the baseline.
+    pub fn accept_content(&self, check_id: &str) -> bool {
+        self.content.accept(check_id)
     }
 
-    pub async fn api_vitally(&self) -> Vec<String> {
-        let mut ret = Vec::new();
-        let client = Client::new();
-        if let Some(ref api_tests) = self.configs.api_tests {
-            for test in api_tests.iter() {
-                self.make_request(&test, &client, &mut ret).await;
-            }
-        }
-        ret
+    pub async fn summary(&self) -> Vec<CheckResult> {
+        self.run_checks(&self.checks()).await
     }
 
-    pub async fn frontend_vitaly(&self) -> Vec<String> {
-        let mut ret = Vec::new();
-        let client = Client::new();
-        if let Some(ref frontend_tests) = self.configs.frontend_tests {
-            for test in frontend_tests.iter() {
-                self.make_request(&test, &client, &mut ret).await;
-            }
-        }
-        ret
+    /// Run only the checks with the given ids.
+    pub async fn run_checks_by_id(&self, ids: &[String]) -> Vec<CheckResult> {
+        let checks: Vec<Check> = self.checks().into_iter()
+            .filter(|check| ids.contains(&check.id()))
+            .collect();
+        self.run_checks(&checks).await
     }
 
-    pub async fn certificates_vitaly(&self) -> Vec<String> {
-        let mut ret = Vec::new();
-        if let Some(ref ssl_tests) = self.configs.ssl_tests {
-            for get in ssl_tests.iter() {
-                let Get { url } = get;
+    pub async fn api_vitally(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Api).await
+    }
 
-                match CheckSSL::from_domain(url.as_str()) {
-                    Ok(_cert) => {
-                        println!("Cert for url [{}] is ok.", url);
-                    }
-                    Err(_) => {
-                        let msg = format!("❌ Error with cert, url: {}.", url).parse_text_to_markdown();
-                        println!("{msg}");
-                        ret.push(msg);
-                    }
-                };
-            }
-        }
-        ret
+    pub async fn frontend_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Frontend).await
+    }
+
+    pub async fn certificates_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Certificate).await
     }
 
-    async fn make_request(&self, test: &RouteTest, client: &Client, ret: &mut Vec<String>) {
-        match test {
-            RouteTest::POST(post) => {
-                self.post_request(post, client, ret).await;
+    pub async fn tcp_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Tcp).await
+    }
+
+    pub async fn dns_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Dns).await
+    }
+
+    pub async fn scenarios_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Scenario).await
+    }
+
+    pub async fn sitemaps_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Sitemap).await
+    }
+
+    pub async fn crawls_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Crawl).await
+    }
+
+    pub async fn heartbeats_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Heartbeat).await
+    }
+
+    pub async fn commands_vitaly(&self) -> Vec<CheckResult> {
+        self.run_checks_of_kind(CheckKind::Command).await
+    }
+
+    async fn run_checks_of_kind(&self, kind: CheckKind) -> Vec<CheckResult> {
+        let checks: Vec<Check> = self.checks().into_iter()
+            .filter(|check| check.kind() == kind)
+            .collect();
+        self.run_checks(&checks).await
+    }
+
+    /// Run a check scheduled by the monitor, only these runs register the baseline of the content.
+    pub async fn run_scheduled_check(&self, check: &Check, client: &Client) -> CheckResult {
+        self.run_check(check, client, true).await
+    }
+
+    async fn run_checks(&self, checks: &[Check]) -> Vec<CheckResult> {
+        let client = Client::new();
+        join_all(checks.iter().map(|check| self.run_check(check, &client, false))).await
+    }
+
+    async fn run_check(&self, check: &Check, client: &Client, scheduled: bool) -> CheckResult {
+        match check {
+            Check::Route(kind, test) => self.make_request(test, *kind, client, scheduled).await,
+            Check::Certificate(ssl) => self.check_certificate(ssl).await,
+            Check::Tcp(tcp) => {
+                let _permit = self.limiter.acquire(&tcp.host).await;
+                check_tcp(tcp, CheckResult::new(tcp.id(), CheckKind::Tcp, tcp.target())).await
+            }
+            Check::Dns(dns) => {
+                let _permit = self.limiter.acquire(&dns.name).await;
+                check_dns(dns, CheckResult::new(dns.id(), CheckKind::Dns, dns.name.clone())).await
+            }
+            Check::Scenario(scenario) => self.run_scenario(scenario, client).await,
+            Check::Sitemap(sitemap) => {
+                let result = CheckResult::new(sitemap.id(), CheckKind::Sitemap, sitemap.url.clone());
+                check_sitemap(sitemap, client, &self.limiter, result).await
+            }
+            Check::Crawl(crawl) => {
+                let result = CheckResult::new(crawl.id(), CheckKind::Crawl, crawl.url.clone());
+                check_crawl(crawl, client, &self.limiter, result).await
+            }
+            Check::Heartbeat(heartbeat) => {
+                let result = CheckResult::new(heartbeat.id(), CheckKind::Heartbeat, heartbeat.name.clone());
+                self.heartbeats.check(heartbeat, result, SystemTime::now())
             }
-            RouteTest::GET(get) => {
-                self.get_request(get, client, ret).await;
+            Check::Command(command) => {
+                let result = CheckResult::new(command.id(), CheckKind::Command, command.command.clone());
+                check_command(command, result).await
             }
         }
     }
 
-    async fn post_request(&self, post: &Post, client: &Client, ret: &mut Vec<String>) {
-        let Post {
-            url,
-            body,
-            content_type,
-        } = post;
-        let times_to_retry = self.configs.times_to_retry.unwrap();
-        let mut times = 0;
+    async fn check_certificate(&self, ssl: &Ssl) -> CheckResult {
+        let result = CheckResult::new(ssl.id(), CheckKind::Certificate, ssl.url.clone());
+        let _permit = self.limiter.acquire(&ssl.url).await;
+
+        let start = Instant::now();
+        let result = check_certificate(ssl, result).await;
+        CheckResult { latency: Some(start.elapsed()), ..result }
+    }
+
+    async fn make_request(&self, test: &RouteTest, kind: CheckKind, client: &Client, scheduled: bool) -> CheckResult {
+        let result = CheckResult::new(test.id(), kind, test.url().to_string());
+        let url = match Url::parse(test.url()) {
+            Ok(url) => url,
+            Err(err) => return result.fail(FailureReason::Request, format!("invalid url: {}", err)),
+        };
+        let _permit = self.limiter.acquire_url(&url).await;
+
+        let request = match test.request(client) {
+            Ok(request) => request,
+            Err(err) => return result.fail(FailureReason::Request, err),
+        };
+        self.send_request(test, request, result, scheduled).await
+    }
+
+    /// Send the request with the retry policy of the test, the failed requests are retried (and the
+    /// responses with an unexpected status when `retry_on_status` is enabled) waiting the backoff delay.
+    async fn send_request(
+        &self,
+        test: &RouteTest,
+        request: RequestBuilder,
+        mut result: CheckResult,
+        scheduled: bool,
+    ) -> CheckResult {
+        let policy = test.retry();
+        let attempts = policy.attempts(self.configs.times_to_retry.unwrap());
+        let retry_on_status = policy.retry_on_status.unwrap_or(false);
+        let mut attempt = 0;
         loop {
-            times += 1;
-            let res_value = client
-                .post(url.clone())
-                .header("Content-Type", content_type)
-                .body(body.to_owned())
-                .timeout(Duration::new(5, 0))
-                .send()
-                .await;
+            attempt += 1;
+            result.attempts = Some(attempt);
+            let request = request.try_clone().expect("the body of the request is not a stream");
+
+            let start = Instant::now();
+            let res_value = request.timeout(policy.timeout()).send().await;
+            result.latency = Some(start.elapsed());
 
             match res_value {
-                Ok(res) => match res.status() {
-                    reqwest::StatusCode::OK => {
-                        println!("Url POST [{}] is OK.", url);
-                        break;
-                    }
-                    _ => {
-                        ret.push(format!(
-                            "❌ The url POST [{}] fails and return an status {}.",
-                            url,
-                            res.status()
-                        ).parse_text_to_markdown());
-                        break;
+                Ok(res) => {
+                    let unexpected_status = test.assertions().check_status(res.status()).is_err();
+                    if !(retry_on_status && unexpected_status && attempt < attempts) {
+                        let content_change = test.content_change();
+                        let result = self.validate_response(res, test.assertions(), content_change, result, start, scheduled)
+                            .await;
+                        if result.is_ok() {
+                            println!("Url {} [{}] is OK.", test.method(), test.url());
+                        }
+                        return result;
                     }
-                },
+                }
                 Err(err) => {
-                    if times >= times_to_retry {
+                    if attempt >= attempts {
                         print!("Error: {:?}", err);
-                        ret.push(format!("❌ The url POST [{}] fails.", url, ).parse_text_to_markdown());
-                        break;
+                        return result.fail(FailureReason::Request, err.to_string());
                     }
                 }
             }
+            sleep(policy.delay(attempt)).await;
         }
     }
 
-    async fn get_request(&self, get: &Get, client: &Client, ret: &mut Vec<String>) {
-        let Get { url } = get;
-        let times_to_retry = self.configs.times_to_retry.unwrap();
-        let mut times = 0;
-        loop {
-            times += 1;
-            let res_value = client.get(url.to_owned()).send().await;
-            match res_value {
-                Ok(res) => match res.status() {
-                    reqwest::StatusCode::OK => {
-                        println!("Url GET [{}] is OK.", url);
-                        break;
-                    }
-                    _ => {
-                        ret.push(format!(
-                            "❌ The url GET [{}] fails and return an status {}.",
-                            url,
-                            res.status()
-                        ).parse_text_to_markdown());
-                        break;
-                    }
-                },
+    /// Run the steps of the scenario in order, the failures are reported with the number of the step.
+    /// Every step takes a permit of the limiter of its host.
+    async fn run_scenario(&self, scenario: &Scenario, client: &Client) -> CheckResult {
+        let mut result = CheckResult::new(scenario.id(), CheckKind::Scenario, scenario.url().to_string());
+
+        let mut variables = HashMap::new();
+        let start = Instant::now();
+        for (index, template) in scenario.steps.iter().enumerate() {
+            let step_error = |step: &Step, err: &str| format!("step {} ({} {}): {}", index + 1, step.method(), step.url, err);
+            let step = match template.render(&variables) {
+                Ok(step) => step,
+                Err(err) => return result.fail(FailureReason::Capture, step_error(template, &err)),
+            };
+            let fail = |result: CheckResult, reason: FailureReason, err: String| result.fail(reason, step_error(&step, &err));
+            let request = http_request(
+                client, &step.method(), &step.url, step.headers.as_ref(), step.body.as_ref(), step.auth.as_ref(),
+            );
+            let request = match request {
+                Ok(request) => request,
+                Err(err) => return fail(result, FailureReason::Request, err),
+            };
+            let url = match Url::parse(&step.url) {
+                Ok(url) => url,
+                Err(err) => return fail(result, FailureReason::Request, format!("invalid url: {}", err)),
+            };
+            let _permit = self.limiter.acquire_url(&url).await;
+
+            let step_start = Instant::now();
+            let res = match request.timeout(DEFAULT_REQUEST_TIMEOUT).send().await {
+                Ok(res) => res,
                 Err(err) => {
-                    if times >= times_to_retry {
-                        print!("Error: {:?}", err);
-                        ret.push(format!("❌ The url GET [{}] fails.", url).parse_text_to_markdown());
-                        break;
+                    result.latency = Some(start.elapsed());
+                    return fail(result, FailureReason::Request, err.to_string());
+                }
+            };
+            let status = res.status();
+            let headers: HeaderMap = res.headers().clone();
+            let body = WebsiteService::read_body(res, step.assertions.max_body_size).await;
+
+            result.http_code = Some(status.as_u16());
+            result.latency = Some(start.elapsed());
+            result = WebsiteService::check_response(
+                status, body.as_deref(), &step.assertions, result, step_start.elapsed(),
+            );
+            if !result.is_ok() {
+                result.error = result.error.map(|err| step_error(&step, &err));
+                return result;
+            }
+
+            for (name, capture) in step.capture.iter().flatten() {
+                match capture.extract(&headers, body.as_deref().unwrap_or_default()) {
+                    Ok(value) => {
+                        variables.insert(name.clone(), value);
                     }
+                    Err(err) => return fail(result, FailureReason::Capture, err),
                 }
             }
         }
+
+        println!("Scenario [{}] is OK.", scenario.name);
+        result
+    }
+
+    async fn validate_response(
+        &self,
+        res: Response,
+        assertions: &ResponseAssertions,
+        content_change: Option<&ContentChange>,
+        mut result: CheckResult,
+        start: Instant,
+        scheduled: bool,
+    ) -> CheckResult {
+        let status = res.status();
+        result.http_code = Some(status.as_u16());
+        let ttfb = start.elapsed();
+        // the body is only downloaded when it's validated
+        let body = if assertions.needs_body() || content_change.is_some() {
+            WebsiteService::read_body(res, assertions.max_body_size).await
+        } else {
+            Ok(Vec::new())
+        };
+        let total = start.elapsed();
+
+        result.latency = Some(total);
+        result.timings = Some(Timings { ttfb: Some(ttfb), total: Some(total) });
+
+        let result = WebsiteService::check_response(status, body.as_deref(), assertions, result, total);
+        match (content_change, body) {
+            (Some(content_change), Ok(body)) if result.status != CheckStatus::Down => {
+                self.check_content(content_change, &body, result, scheduled)
+            }
+            _ => result,
+        }
+    }
+
+    /// Read the body of the response, the download stops once it exceeds the max size.
+    async fn read_body(mut res: Response, max_size: Option<usize>) -> Result<Vec<u8>, String> {
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await.map_err(|err| err.to_string())? {
+            body.extend_from_slice(&chunk);
+            if max_size.is_some_and(|max_size| body.len() > max_size) {
+                break;
+            }
+        }
+        Ok(body)
+    }
+
+    /// Compare the body of the response with the baseline, the changes are reported with the diff until
+    /// they're accepted. Only the scheduled runs register the baseline.
+    fn check_content(
+        &self,
+        content_change: &ContentChange,
+        body: &[u8],
+        result: CheckResult,
+        scheduled: bool,
+    ) -> CheckResult {
+        let content = match content_change.normalize(&String::from_utf8_lossy(body)) {
+            Ok(content) => content,
+            Err(err) => return result.fail(FailureReason::Body, err),
+        };
+        match self.content.compare(&result.check_id, content, scheduled) {
+            Some(diff) => CheckResult {
+                diff: Some(diff),
+                ..result.fail(FailureReason::ContentChanged, "the content changed".to_string())
+            },
+            None => result,
+        }
+    }
+
+    fn check_response(
+        status: StatusCode,
+        body: Result<&[u8], &String>,
+        assertions: &ResponseAssertions,
+        result: CheckResult,
+        total: Duration,
+    ) -> CheckResult {
+        if let Err(err) = assertions.check_status(status) {
+            return result.fail(FailureReason::Status, err);
+        }
+        let body = match body {
+            Ok(body) => body,
+            Err(err) => return result.fail(FailureReason::Body, format!("error reading the body: {}", err)),
+        };
+        if let Err(err) = assertions.check_body(body) {
+            return result.fail(FailureReason::Body, err);
+        }
+        if let Err(err) = assertions.check_json(body) {
+            return result.fail(FailureReason::Json, err);
+        }
+        match assertions.check_latency(total) {
+            Err((CheckStatus::Down, err)) => result.fail(FailureReason::Latency, err),
+            Err((_, err)) => result.degrade(FailureReason::Latency, err),
+            Ok(()) => result,
+        }
     }
 }
@@ -0,0 +1,416 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use regex::Regex;
+use reqwest::StatusCode;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::monitor::state::CheckStatus;
+
+/// Accepted status code of a response, a single code (`204`) or a range (`"200-299"` or `"2xx"`).
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ExpectedStatus {
+    Code(u16),
+    Range(String),
+}
+
+impl ExpectedStatus {
+    /// Codes accepted by the status, the invalid ranges are rejected when the configurations are loaded.
+    pub fn codes(&self) -> Result<RangeInclusive<u16>, String> {
+        let range = match self {
+            ExpectedStatus::Code(code) => return Ok(*code..=*code),
+            ExpectedStatus::Range(range) => range.trim().to_lowercase(),
+        };
+        let invalid = || format!("invalid expected status \"{}\"", self);
+        let code = |code: &str| code.trim().parse::<u16>().ok().filter(|code| (100..=599).contains(code));
+        if let Some(class) = range.strip_suffix("xx") {
+            let class = class.parse::<u16>().ok().filter(|class| (1..=5).contains(class)).ok_or_else(invalid)?;
+            return Ok(class * 100..=class * 100 + 99);
+        }
+        match range.split_once('-') {
+            Some((from, to)) => match (code(from), code(to)) {
+                (Some(from), Some(to)) if from <= to => Ok(from..=to),
+                _ => Err(invalid()),
+            },
+            None => code(&range).map(|code| code..=code).ok_or_else(invalid),
+        }
+    }
+
+    pub fn matches(&self, code: u16) -> bool {
+        self.codes().is_ok_and(|codes| codes.contains(&code))
+    }
+}
+
+impl std::fmt::Display for ExpectedStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectedStatus::Code(code) => write!(f, "{}", code),
+            ExpectedStatus::Range(range) => write!(f, "{}", range),
+        }
+    }
+}
+
+/// Regex of the configurations, compiled when they are loaded.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern, String> {
+        Regex::new(pattern)
+            .map(Pattern)
+            .map_err(|err| format!("invalid regex \"{}\": {}", pattern, err))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Pattern, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Pattern::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Validation of a value of a json response. The `path` is a JSON pointer (`/data/posts/0/id`) or a
+/// simple JSONPath (`$.data.posts[0].id`), without wildcards or filters.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct JsonAssertion {
+    pub path: String,
+    pub equals: Option<Value>,
+    pub exists: Option<bool>,
+    pub greater_than: Option<f64>,
+    pub less_than: Option<f64>,
+}
+
+impl JsonAssertion {
+    pub fn check(&self, json: &Value) -> Result<(), String> {
+        let pointer = json_path_to_pointer(&self.path)?;
+        let value = json.pointer(&pointer);
+
+        match (self.exists, value) {
+            (Some(true), None) => return Err(format!("json path {} doesn't exist", self.path)),
+            (Some(false), Some(value)) => {
+                return Err(format!("json path {} exists with value {}", self.path, value));
+            }
+            _ => {}
+        }
+
+        if let Some(ref expected) = self.equals {
+            if value != Some(expected) {
+                return Err(format!("json path {} is {} (expected {})", self.path, display_value(value), expected));
+            }
+        }
+
+        if self.greater_than.is_some() || self.less_than.is_some() {
+            let number = value.and_then(|v| v.as_f64())
+                .ok_or(format!("json path {} is {} (expected a number)", self.path, display_value(value)))?;
+            if let Some(min) = self.greater_than {
+                if number <= min {
+                    return Err(format!("json path {} is {} (expected greater than {})", self.path, number, min));
+                }
+            }
+            if let Some(max) = self.less_than {
+                if number >= max {
+                    return Err(format!("json path {} is {} (expected less than {})", self.path, number, max));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn display_value(value: Option<&Value>) -> String {
+    value.map(|v| v.to_string()).unwrap_or("missing".to_string())
+}
+
+/// Convert a JSONPath (`$.data['posts'][0]`) to a JSON pointer (`/data/posts/0`), JSON pointers are
+/// returned without changes.
+pub(crate) fn json_path_to_pointer(path: &str) -> Result<String, String> {
+    if path.is_empty() || path.starts_with('/') {
+        return Ok(path.to_string());
+    }
+    let invalid = || format!("invalid json path \"{}\"", path);
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+
+    let mut pointer = String::new();
+    while !rest.is_empty() {
+        let token;
+        if let Some(field) = rest.strip_prefix('.') {
+            let end = field.find(['.', '[']).unwrap_or(field.len());
+            token = &field[..end];
+            rest = &field[end..];
+        } else if let Some(index) = rest.strip_prefix('[') {
+            let end = index.find(']').ok_or_else(invalid)?;
+            token = index[..end].trim_matches(|c| c == '\'' || c == '"');
+            rest = &index[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+        if token.is_empty() || token == "*" {
+            return Err(invalid());
+        }
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    }
+    Ok(pointer)
+}
+
+/// Validations of the response of an http test. Without `expected_status` only `200` is accepted.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ResponseAssertions {
+    pub expected_status: Option<Vec<ExpectedStatus>>,
+    pub body_contains: Option<Vec<String>>,
+    pub body_not_contains: Option<Vec<String>>,
+    pub body_regex: Option<Pattern>,
+    /// Max size of the body in bytes.
+    pub max_body_size: Option<usize>,
+    pub json_assertions: Option<Vec<JsonAssertion>>,
+    /// Response time (in milliseconds) to consider the service degraded.
+    pub warn_latency_ms: Option<u64>,
+    /// Response time (in milliseconds) to consider the service down.
+    pub critical_latency_ms: Option<u64>,
+}
+
+impl ResponseAssertions {
+    pub fn check_status(&self, status: StatusCode) -> Result<(), String> {
+        match self.expected_status {
+            Some(ref expected) => {
+                if expected.iter().any(|e| e.matches(status.as_u16())) {
+                    return Ok(());
+                }
+                let expected: Vec<String> = expected.iter().map(|e| e.to_string()).collect();
+                Err(format!("unexpected status {} (expected {})", status, expected.join(", ")))
+            }
+            None if status == StatusCode::OK => Ok(()),
+            None => Err(format!("unexpected status {}", status)),
+        }
+    }
+
+    /// The body of the response is validated, otherwise it isn't downloaded.
+    pub fn needs_body(&self) -> bool {
+        self.body_contains.is_some()
+            || self.body_not_contains.is_some()
+            || self.body_regex.is_some()
+            || self.max_body_size.is_some()
+            || self.json_assertions.is_some()
+    }
+
+    /// Validate the assertions when the configurations are loaded, the regexes are validated when
+    /// they are deserialized.
+    pub fn validate(&self) -> Result<(), String> {
+        for expected in self.expected_status.iter().flatten() {
+            expected.codes()?;
+        }
+        Ok(())
+    }
+
+    /// Validate the body, it's only read until it exceeds `max_body_size` so its size can be greater
+    /// than the real size of the body.
+    pub fn check_body(&self, body: &[u8]) -> Result<(), String> {
+        if let Some(max_body_size) = self.max_body_size {
+            if body.len() > max_body_size {
+                return Err(format!("body size exceeds the max of {} bytes", max_body_size));
+            }
+        }
+
+        let text = String::from_utf8_lossy(body);
+        for expected in self.body_contains.iter().flatten() {
+            if !text.contains(expected.as_str()) {
+                return Err(format!("body doesn't contain \"{}\"", expected));
+            }
+        }
+        for unexpected in self.body_not_contains.iter().flatten() {
+            if text.contains(unexpected.as_str()) {
+                return Err(format!("body contains \"{}\"", unexpected));
+            }
+        }
+        if let Some(ref pattern) = self.body_regex {
+            if !pattern.is_match(&text) {
+                return Err(format!("body doesn't match the regex \"{}\"", pattern.as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the response time, if a threshold is exceeded the status of the check is returned
+    /// with the error.
+    pub fn check_latency(&self, latency: Duration) -> Result<(), (CheckStatus, String)> {
+        let latency_ms = latency.as_millis();
+        if let Some(critical) = self.critical_latency_ms {
+            if latency_ms >= critical as u128 {
+                return Err((
+                    CheckStatus::Down,
+                    format!("latency of {} ms exceeds the critical threshold of {} ms", latency_ms, critical),
+                ));
+            }
+        }
+        if let Some(warn) = self.warn_latency_ms {
+            if latency_ms >= warn as u128 {
+                return Err((
+                    CheckStatus::Degraded,
+                    format!("latency of {} ms exceeds the warning threshold of {} ms", latency_ms, warn),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check_json(&self, body: &[u8]) -> Result<(), String> {
+        let Some(ref json_assertions) = self.json_assertions else {
+            return Ok(());
+        };
+        let json: Value = serde_json::from_slice(body)
+            .map_err(|err| format!("body is not a valid json: {}", err))?;
+        for assertion in json_assertions.iter() {
+            assertion.check(&json)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use super::{json_path_to_pointer, ExpectedStatus, JsonAssertion, Pattern, ResponseAssertions};
+    use crate::monitor::state::CheckStatus;
+
+    #[test]
+    fn expected_status_test() {
+        assert!(ExpectedStatus::Code(204).matches(204));
+        assert!(!ExpectedStatus::Code(204).matches(200));
+        assert!(ExpectedStatus::Range("2xx".to_string()).matches(201));
+        assert!(!ExpectedStatus::Range("2xx".to_string()).matches(301));
+        assert!(ExpectedStatus::Range("300-399".to_string()).matches(301));
+        assert!(!ExpectedStatus::Range("300-399".to_string()).matches(401));
+        assert!(!ExpectedStatus::Range("invalid".to_string()).matches(200));
+
+        let invalid = ["5xx-", "300-200", "9xx", "200-", "1000"];
+        for range in invalid {
+            let assertions = ResponseAssertions {
+                expected_status: Some(vec![ExpectedStatus::Code(200), ExpectedStatus::Range(range.to_string())]),
+                ..Default::default()
+            };
+            assert_eq!(assertions.validate(), Err(format!("invalid expected status \"{}\"", range)));
+        }
+        let valid = ResponseAssertions {
+            expected_status: Some(vec![ExpectedStatus::Range("2xx".to_string()), ExpectedStatus::Range(" 300-399".to_string())]),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+    }
+
+    #[test]
+    fn check_status_test() {
+        let default = ResponseAssertions::default();
+        assert!(default.check_status(StatusCode::OK).is_ok());
+        assert!(default.check_status(StatusCode::NO_CONTENT).is_err());
+
+        let assertions = ResponseAssertions {
+            expected_status: Some(vec![ExpectedStatus::Code(401), ExpectedStatus::Range("2xx".to_string())]),
+            ..Default::default()
+        };
+        assert!(assertions.check_status(StatusCode::NO_CONTENT).is_ok());
+        assert!(assertions.check_status(StatusCode::UNAUTHORIZED).is_ok());
+        assert_eq!(
+            assertions.check_status(StatusCode::NOT_FOUND),
+            Err("unexpected status 404 Not Found (expected 401, 2xx)".to_string())
+        );
+    }
+
+    #[test]
+    fn check_body_test() {
+        let assertions = ResponseAssertions {
+            body_contains: Some(vec!["Binary Coffee".to_string()]),
+            body_not_contains: Some(vec!["Internal error".to_string()]),
+            body_regex: Some(Pattern::new("posts: \\d+").unwrap()),
+            max_body_size: Some(100),
+            ..Default::default()
+        };
+        assert!(assertions.needs_body());
+        assert!(!ResponseAssertions::default().needs_body());
+        assert!(assertions.check_body(b"Binary Coffee, posts: 42").is_ok());
+        assert_eq!(
+            assertions.check_body(b"Welcome, posts: 42"),
+            Err("body doesn't contain \"Binary Coffee\"".to_string())
+        );
+        assert_eq!(
+            assertions.check_body(b"Binary Coffee, posts: 42. Internal error"),
+            Err("body contains \"Internal error\"".to_string())
+        );
+        assert_eq!(
+            assertions.check_body(b"Binary Coffee, posts: none"),
+            Err("body doesn't match the regex \"posts: \\d+\"".to_string())
+        );
+        assert_eq!(assertions.check_body(&[b'a'; 101]), Err("body size exceeds the max of 100 bytes".to_string()));
+
+        assert!(Pattern::new("posts: (\\d+").unwrap_err().starts_with("invalid regex \"posts: (\\d+\""));
+        let invalid = serde_json::from_str::<ResponseAssertions>(r#"{"body_regex": "posts: (\\d+"}"#);
+        assert!(invalid.unwrap_err().to_string().starts_with("invalid regex \"posts: (\\d+\""));
+    }
+
+    #[test]
+    fn json_path_to_pointer_test() {
+        assert_eq!(json_path_to_pointer("$.data.posts[0].id"), Ok("/data/posts/0/id".to_string()));
+        assert_eq!(json_path_to_pointer("$['data']['a/b']"), Ok("/data/a~1b".to_string()));
+        assert_eq!(json_path_to_pointer("/data/posts/0"), Ok("/data/posts/0".to_string()));
+        assert_eq!(json_path_to_pointer("$"), Ok("".to_string()));
+        assert!(json_path_to_pointer("data.posts").is_err());
+        assert!(json_path_to_pointer("$.posts[*]").is_err());
+    }
+
+    #[test]
+    fn check_json_test() {
+        let assertions = ResponseAssertions {
+            json_assertions: Some(vec![
+                JsonAssertion { path: "$.errors".to_string(), exists: Some(false), ..Default::default() },
+                JsonAssertion { path: "$.data.status".to_string(), equals: Some(json!("ok")), ..Default::default() },
+                JsonAssertion { path: "/data/count".to_string(), greater_than: Some(0.0), ..Default::default() },
+            ]),
+            ..Default::default()
+        };
+        assert!(assertions.check_json(br#"{"data": {"status": "ok", "count": 3}}"#).is_ok());
+        assert_eq!(
+            assertions.check_json(br#"{"data": null, "errors": [{"message": "boom"}]}"#),
+            Err("json path $.errors exists with value [{\"message\":\"boom\"}]".to_string())
+        );
+        assert_eq!(
+            assertions.check_json(br#"{"data": {"status": "ok", "count": 0}}"#),
+            Err("json path /data/count is 0 (expected greater than 0)".to_string())
+        );
+        assert!(assertions.check_json(b"<html></html>").unwrap_err().starts_with("body is not a valid json"));
+    }
+
+    #[test]
+    fn check_latency_test() {
+        let assertions = ResponseAssertions {
+            warn_latency_ms: Some(1000),
+            critical_latency_ms: Some(5000),
+            ..Default::default()
+        };
+        assert!(assertions.check_latency(Duration::from_millis(200)).is_ok());
+        assert_eq!(
+            assertions.check_latency(Duration::from_millis(1200)),
+            Err((CheckStatus::Degraded, "latency of 1200 ms exceeds the warning threshold of 1000 ms".to_string()))
+        );
+        assert_eq!(assertions.check_latency(Duration::from_secs(9)).unwrap_err().0, CheckStatus::Down);
+        assert!(ResponseAssertions::default().check_latency(Duration::from_secs(60)).is_ok());
+    }
+}
@@ -0,0 +1,65 @@
+use std::env;
+
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+
+/// Authentication of an http test. The secrets can reference environment variables with the format
+/// `${VAR_NAME}`, so they don't need to be stored in the configuration file.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+    Header { name: String, value: String },
+}
+
+impl Auth {
+    pub fn apply(&self, request: RequestBuilder) -> Result<RequestBuilder, String> {
+        Ok(match self {
+            Auth::Basic { username, password } => {
+                request.basic_auth(resolve_secret(username)?, Some(resolve_secret(password)?))
+            }
+            Auth::Bearer { token } => request.bearer_auth(resolve_secret(token)?),
+            Auth::Header { name, value } => request.header(name, resolve_secret(value)?),
+        })
+    }
+}
+
+/// Replace the references to environment variables (`${VAR_NAME}`) with their values.
+pub fn resolve_secret(value: &str) -> Result<String, String> {
+    let mut resolved = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..].find('}')
+            .ok_or(format!("unclosed environment variable in \"{}\"", value))?;
+        let name = &rest[start + 2..start + end];
+        let var = env::var(name).map_err(|_| format!("environment variable {} is not defined", name))?;
+
+        resolved.push_str(&rest[..start]);
+        resolved.push_str(&var);
+        rest = &rest[start + end + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::resolve_secret;
+
+    #[test]
+    fn resolve_secret_test() {
+        env::set_var("SM_TEST_SECRET", "s3cr3t");
+
+        assert_eq!(resolve_secret("plain token"), Ok("plain token".to_string()));
+        assert_eq!(resolve_secret("${SM_TEST_SECRET}"), Ok("s3cr3t".to_string()));
+        assert_eq!(resolve_secret("Token ${SM_TEST_SECRET}!"), Ok("Token s3cr3t!".to_string()));
+        assert_eq!(
+            resolve_secret("${SM_TEST_UNDEFINED}"),
+            Err("environment variable SM_TEST_UNDEFINED is not defined".to_string())
+        );
+        assert!(resolve_secret("${SM_TEST_SECRET").is_err());
+    }
+}
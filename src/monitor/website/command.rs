@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::process;
+use tokio::time::timeout;
+
+use crate::monitor::website::auth::resolve_secret;
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+const DEFAULT_COMMAND_TIMEOUT_MS: u64 = 10000;
+
+/// Local command executed as a check, the exit codes are the ones of the Nagios plugins: `0` OK,
+/// `1` WARNING, `2` CRITICAL and `3` UNKNOWN.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Command {
+    pub name: String,
+    pub command: String,
+    pub args: Option<Vec<String>>,
+    /// Environment variables of the command, the values can reference environment variables of the
+    /// service (`${VAR_NAME}`).
+    pub env: Option<HashMap<String, String>>,
+    /// Max time (in milliseconds) to execute the command.
+    pub timeout: Option<u64>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Command {
+    pub fn id(&self) -> String {
+        format!("COMMAND {}", self.name)
+    }
+}
+
+/// Performance data of the output of a Nagios plugin: `'label'=value[UOM];[warn];[crit];[min];[max]`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PerfData {
+    pub label: String,
+    pub value: f64,
+    pub unit: String,
+    pub warn: Option<String>,
+    pub crit: Option<String>,
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+/// Items of the performance data, they are separated by spaces but the labels can be quoted.
+fn split_perfdata(perfdata: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (index, c) in perfdata.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    items.push(&perfdata[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(start) = start {
+        items.push(&perfdata[start..]);
+    }
+    items
+}
+
+/// Split the first line of the output in the text and the performance data.
+pub fn parse_output(stdout: &str) -> (String, Vec<PerfData>) {
+    let line = stdout.lines().next().unwrap_or_default();
+    let (text, perfdata) = line.split_once('|').unwrap_or((line, ""));
+
+    let perfdata = split_perfdata(perfdata).into_iter()
+        .filter_map(|item| {
+            let (label, data) = item.rsplit_once('=')?;
+            let mut fields = data.split(';');
+            let raw_value = fields.next()?;
+            let number_end = raw_value.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+                .unwrap_or(raw_value.len());
+            let mut next = || fields.next().filter(|field| !field.is_empty()).map(|field| field.to_string());
+            Some(PerfData {
+                label: label.trim_matches('\'').to_string(),
+                value: raw_value[..number_end].parse().ok()?,
+                unit: raw_value[number_end..].to_string(),
+                warn: next(),
+                crit: next(),
+                min: next(),
+                max: next(),
+            })
+        })
+        .collect();
+    (text.trim().to_string(), perfdata)
+}
+
+pub async fn check_command(command: &Command, mut result: CheckResult) -> CheckResult {
+    let max_time = Duration::from_millis(command.timeout.unwrap_or(DEFAULT_COMMAND_TIMEOUT_MS));
+    let mut process = process::Command::new(&command.command);
+    process.args(command.args.iter().flatten())
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    for (name, value) in command.env.iter().flatten() {
+        match resolve_secret(value) {
+            Ok(value) => process.env(name, value),
+            Err(err) => return result.fail(FailureReason::Command, format!("UNKNOWN: {}", err)),
+        };
+    }
+
+    let start = Instant::now();
+    let output = timeout(max_time, process.output()).await;
+    result.latency = Some(start.elapsed());
+    let output = match output {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => return result.fail(FailureReason::Command, format!("UNKNOWN: error executing the command: {}", err)),
+        Err(_) => {
+            return result.fail(
+                FailureReason::Command,
+                format!("UNKNOWN: timeout after {} ms", max_time.as_millis()),
+            );
+        }
+    };
+
+    let (text, perfdata) = parse_output(&String::from_utf8_lossy(&output.stdout));
+    result.output = Some(text.clone()).filter(|text| !text.is_empty());
+    result.perfdata = Some(perfdata).filter(|perfdata| !perfdata.is_empty());
+    let text = match (text.is_empty(), output.status.code()) {
+        (false, _) => text,
+        (true, Some(code)) => format!("exit code {}", code),
+        (true, None) => "terminated by a signal".to_string(),
+    };
+    match output.status.code() {
+        Some(0) => {
+            println!("Command [{}] is OK.", command.name);
+            result
+        }
+        Some(1) => result.degrade(FailureReason::Command, format!("WARNING: {}", text)),
+        Some(2) => result.fail(FailureReason::Command, format!("CRITICAL: {}", text)),
+        _ => result.fail(FailureReason::Command, format!("UNKNOWN: {}", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{check_command, parse_output, Command, PerfData};
+    use crate::monitor::state::CheckStatus;
+    use crate::monitor::website::models::{CheckKind, CheckResult};
+
+    fn script(name: &str, script: &str) -> Command {
+        Command {
+            name: name.to_string(),
+            command: "sh".to_string(),
+            args: Some(vec!["-c".to_string(), script.to_string()]),
+            ..Default::default()
+        }
+    }
+
+    async fn run(command: &Command) -> CheckResult {
+        check_command(command, CheckResult::new(command.id(), CheckKind::Command, command.command.clone())).await
+    }
+
+    #[test]
+    fn parse_output_test() {
+        let (text, perfdata) = parse_output("DISK OK - free space: / 3326 MB (56%) | /=2643MB;5948;5958;0;5968 'queue depth'=3\nmore lines");
+
+        assert_eq!(text, "DISK OK - free space: / 3326 MB (56%)");
+        assert_eq!(perfdata[0], PerfData {
+            label: "/".to_string(),
+            value: 2643.0,
+            unit: "MB".to_string(),
+            warn: Some("5948".to_string()),
+            crit: Some("5958".to_string()),
+            min: Some("0".to_string()),
+            max: Some("5968".to_string()),
+        });
+        assert_eq!(perfdata[1].label, "queue depth");
+        assert_eq!(perfdata[1].warn, None);
+        assert_eq!(parse_output("OK").0, "OK");
+    }
+
+    #[tokio::test]
+    async fn exit_codes_test() {
+        let ok = run(&script("ok", "echo 'QUEUE OK | depth=3;10;20'")).await;
+        assert_eq!(ok.status, CheckStatus::Up);
+        assert_eq!(ok.output.as_deref(), Some("QUEUE OK"));
+        assert_eq!(ok.perfdata.unwrap()[0].value, 3.0);
+
+        let warning = run(&script("warning", "echo 'QUEUE WARNING'; exit 1")).await;
+        assert_eq!(warning.status, CheckStatus::Degraded);
+        assert_eq!(warning.error.as_deref(), Some("WARNING: QUEUE WARNING"));
+
+        let critical = run(&script("critical", "echo 'QUEUE CRITICAL'; exit 2")).await;
+        assert_eq!(critical.status, CheckStatus::Down);
+        assert_eq!(critical.error.as_deref(), Some("CRITICAL: QUEUE CRITICAL"));
+
+        let unknown = run(&script("unknown", "exit 3")).await;
+        assert_eq!(unknown.error.as_deref(), Some("UNKNOWN: exit code 3"));
+    }
+
+    #[tokio::test]
+    async fn env_and_timeout_test() {
+        let command = Command {
+            env: Some(HashMap::from([("QUEUE".to_string(), "emails".to_string())])),
+            ..script("env", "echo \"$QUEUE OK\"")
+        };
+        assert_eq!(run(&command).await.output.as_deref(), Some("emails OK"));
+
+        let command = Command { timeout: Some(100), ..script("slow", "sleep 5") };
+        let result = run(&command).await;
+        assert_eq!(result.status, CheckStatus::Down);
+        assert_eq!(result.error.as_deref(), Some("UNKNOWN: timeout after 100 ms"));
+
+        let result = run(&Command { command: "/not/a/command".to_string(), ..Default::default() }).await;
+        assert!(result.error.unwrap().starts_with("UNKNOWN: error executing the command"));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use openssl::sha::sha256;
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+/// Max number of lines of the diff included in the alert.
+const MAX_DIFF_LINES: usize = 12;
+
+/// Detection of unexpected changes of the body of a response (defacement, stale or error pages).
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ContentChange {
+    /// Regexes of the dynamic regions removed before the comparison (dates, csrf tokens, ...).
+    pub ignore_regex: Option<Vec<String>>,
+    /// CSS selectors of the dynamic elements removed before the comparison.
+    pub ignore_selectors: Option<Vec<String>>,
+}
+
+impl ContentChange {
+    /// Body of the response without the dynamic regions.
+    pub fn normalize(&self, body: &str) -> Result<String, String> {
+        let mut content = body.to_string();
+        if let Some(ref selectors) = self.ignore_selectors {
+            let html = Html::parse_document(body);
+            content = html.root_element().html();
+            for selector in selectors {
+                let parsed = Selector::parse(selector)
+                    .map_err(|err| format!("invalid css selector {}: {}", selector, err))?;
+                for element in html.select(&parsed) {
+                    content = content.replace(&element.html(), "");
+                }
+            }
+        }
+        for pattern in self.ignore_regex.iter().flatten() {
+            let regex = Regex::new(pattern).map_err(|err| format!("invalid regex {}: {}", pattern, err))?;
+            content = regex.replace_all(&content, "").to_string();
+        }
+        Ok(content)
+    }
+}
+
+struct Baseline {
+    hash: [u8; 32],
+    content: String,
+}
+
+/// Keeps the baseline content of every check to detect the changes. The first content of a scheduled run
+/// of the check is the baseline, and it's kept until the change is accepted, so the check is down while the
+/// content differs.
+pub struct ContentTracker {
+    baselines: Mutex<HashMap<String, Baseline>>,
+}
+
+impl ContentTracker {
+    pub fn new() -> ContentTracker {
+        ContentTracker { baselines: Mutex::new(HashMap::new()) }
+    }
+
+    /// Compare the content of a check with the baseline and return the diff when it changed. Without a
+    /// baseline the content is registered as the baseline only when `register` (the scheduled runs), so a
+    /// manual check can't choose the baseline.
+    pub fn compare(&self, check_id: &str, content: String, register: bool) -> Option<String> {
+        let hash = sha256(content.as_bytes());
+        let mut baselines = self.baselines.lock().unwrap();
+        match baselines.get(check_id) {
+            Some(baseline) if baseline.hash != hash => Some(diff_excerpt(&baseline.content, &content)),
+            Some(_) => None,
+            None => {
+                if register {
+                    baselines.insert(check_id.to_string(), Baseline { hash, content });
+                }
+                None
+            }
+        }
+    }
+
+    /// Accept the change of the content of a check, the next content becomes the baseline. Returns `false`
+    /// when the check doesn't have a baseline.
+    pub fn accept(&self, check_id: &str) -> bool {
+        self.baselines.lock().unwrap().remove(check_id).is_some()
+    }
+}
+
+impl Default for ContentTracker {
+    fn default() -> Self {
+        ContentTracker::new()
+    }
+}
+
+/// First lines of the unified diff between both contents.
+pub fn diff_excerpt(old: &str, new: &str) -> String {
+    let diff = TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(1)
+        .header("baseline", "current")
+        .to_string();
+    let lines: Vec<&str> = diff.lines().collect();
+    if lines.len() <= MAX_DIFF_LINES {
+        return lines.join("\n");
+    }
+    format!("{}\n... {} more lines", lines[..MAX_DIFF_LINES].join("\n"), lines.len() - MAX_DIFF_LINES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_excerpt, ContentChange, ContentTracker};
+
+    #[test]
+    fn normalize_test() {
+        let content = ContentChange {
+            ignore_regex: Some(vec![r"csrf=\w+".to_string()]),
+            ignore_selectors: Some(vec!["#clock".to_string(), ".ads".to_string()]),
+        };
+        let body = |time: &str, token: &str| format!(
+            "<html><body><h1>Binary Coffee</h1><span id=\"clock\">{}</span>\
+            <div class=\"ads\"><p>ad {}</p></div><form action=\"/login?csrf={}\"></form></body></html>",
+            time, time, token,
+        );
+
+        let first = content.normalize(&body("10:00", "abc")).unwrap();
+        assert_eq!(first, content.normalize(&body("10:05", "xyz")).unwrap());
+        assert!(!first.contains("clock"));
+        assert!(first.contains("<h1>Binary Coffee</h1>"));
+
+        let invalid = ContentChange { ignore_selectors: Some(vec!["[".to_string()]), ..Default::default() };
+        assert!(invalid.normalize("<html></html>").is_err());
+    }
+
+    #[test]
+    fn changes_are_reported_until_accepted_test() {
+        let tracker = ContentTracker::new();
+        let diff = "--- baseline\n+++ current\n@@ -1 +1 @@\n-<h1>Binary Coffee</h1>\n+<h1>Hacked</h1>".to_string();
+
+        assert_eq!(tracker.compare("GET /", "<h1>Binary Coffee</h1>\n".to_string(), true), None);
+        assert_eq!(tracker.compare("GET /", "<h1>Binary Coffee</h1>\n".to_string(), true), None);
+        assert_eq!(tracker.compare("GET /", "<h1>Hacked</h1>\n".to_string(), true), Some(diff.clone()));
+        assert_eq!(tracker.compare("GET /", "<h1>Hacked</h1>\n".to_string(), false), Some(diff));
+        assert_eq!(tracker.compare("GET /", "<h1>Binary Coffee</h1>\n".to_string(), true), None);
+
+        assert!(tracker.accept("GET /"));
+        assert!(!tracker.accept("GET /blog"));
+        assert_eq!(tracker.compare("GET /", "<h1>New design</h1>\n".to_string(), true), None);
+        assert_eq!(tracker.compare("GET /", "<h1>New design</h1>\n".to_string(), true), None);
+    }
+
+    #[test]
+    fn manual_checks_do_not_register_the_baseline_test() {
+        let tracker = ContentTracker::new();
+
+        assert_eq!(tracker.compare("GET /", "<h1>Hacked</h1>\n".to_string(), false), None);
+        assert!(!tracker.accept("GET /"));
+        assert_eq!(tracker.compare("GET /", "<h1>Binary Coffee</h1>\n".to_string(), true), None);
+        assert!(tracker.compare("GET /", "<h1>Hacked</h1>\n".to_string(), false).is_some());
+    }
+
+    #[test]
+    fn long_diff_is_truncated_test() {
+        let old: String = (0..20).map(|line| format!("line {}\n", line)).collect();
+        let new: String = (0..20).map(|line| format!("new line {}\n", line)).collect();
+
+        let diff = diff_excerpt(&old, &new);
+        assert_eq!(diff.lines().count(), 13);
+        assert!(diff.ends_with("... 31 more lines"));
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use futures_util::stream::{self, StreamExt};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Url};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::website::limiter::ConcurrencyLimiter;
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+const DEFAULT_CRAWL_DEPTH: usize = 2;
+const DEFAULT_CRAWL_PAGES: usize = 100;
+const CRAWL_CONCURRENCY: usize = 5;
+const CRAWL_TIMEOUT: Duration = Duration::from_secs(10);
+/// The crawl is slower than the other checks, so it runs every hour unless other interval is configured.
+const DEFAULT_CRAWL_INTERVAL: u64 = 3600;
+/// Max number of pages listed in the alert.
+const MAX_LISTED_PAGES: usize = 10;
+
+/// Crawl of the links and assets of a frontend page that are in the same origin.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Crawl {
+    /// Levels of pages whose links are validated, `1` only validates the links of the first page.
+    pub max_depth: Option<usize>,
+    /// Max number of links validated in every execution.
+    pub max_pages: Option<usize>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+/// Crawl check of a frontend test.
+#[derive(Debug, Clone)]
+pub struct CrawlCheck {
+    pub url: String,
+    pub crawl: Crawl,
+    pub settings: CheckSettings,
+}
+
+impl CrawlCheck {
+    pub fn new(url: &str, crawl: &Crawl) -> CrawlCheck {
+        let settings = CheckSettings {
+            interval: crawl.settings.interval.or(Some(DEFAULT_CRAWL_INTERVAL)),
+            ..crawl.settings.clone()
+        };
+        CrawlCheck { url: url.to_string(), crawl: crawl.clone(), settings }
+    }
+
+    pub fn id(&self) -> String {
+        format!("CRAWL {}", self.url)
+    }
+}
+
+/// Links (`href` and `src` attributes) of the page in the same origin, without fragments or duplicates.
+pub fn extract_links(page: &Url, html: &str) -> Vec<Url> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("[href], [src]").unwrap();
+    let mut links: Vec<Url> = Vec::new();
+    for element in document.select(&selector) {
+        let attributes = ["href", "src"].into_iter().filter_map(|name| element.value().attr(name));
+        for attribute in attributes {
+            let Ok(mut link) = page.join(attribute.trim()) else {
+                continue;
+            };
+            link.set_fragment(None);
+            if link.origin() == page.origin() && !links.contains(&link) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+/// Load the url and return the body when it's an html page.
+async fn fetch(client: &Client, limiter: &ConcurrencyLimiter, url: Url) -> (Url, Result<Option<String>, String>) {
+    let _permit = limiter.acquire_url(&url).await;
+    let res = match client.get(url.clone()).timeout(CRAWL_TIMEOUT).send().await {
+        Ok(res) => res,
+        Err(err) if err.is_timeout() => return (url, Err("timeout".to_string())),
+        Err(_) => return (url, Err("request failed".to_string())),
+    };
+    if !res.status().is_success() {
+        return (url, Err(res.status().as_u16().to_string()));
+    }
+    let is_html = res.headers().get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("text/html"))
+        .unwrap_or(false);
+    if !is_html {
+        return (url, Ok(None));
+    }
+    (url, Ok(res.text().await.ok()))
+}
+
+/// Summary of the broken links grouped by page, only the first pages are listed to keep the alert short.
+fn broken_links_error(broken: &BTreeMap<String, Vec<(Url, String)>>) -> String {
+    let total: usize = broken.values().map(|links| links.len()).sum();
+    let mut pages: Vec<String> = broken.iter()
+        .take(MAX_LISTED_PAGES)
+        .map(|(page, links)| {
+            let links: Vec<String> = links.iter().map(|(link, err)| format!("{} ({})", link, err)).collect();
+            format!("{} -> {}", page, links.join(", "))
+        })
+        .collect();
+    if broken.len() > MAX_LISTED_PAGES {
+        pages.push(format!("and {} more pages", broken.len() - MAX_LISTED_PAGES));
+    }
+    format!("{} broken links in {} pages: {}", total, broken.len(), pages.join("; "))
+}
+
+/// Validate the links of the page, every request takes a permit of the limiter of its host.
+pub async fn check_crawl(check: &CrawlCheck, client: &Client, limiter: &ConcurrencyLimiter, mut result: CheckResult) -> CheckResult {
+    let start = Instant::now();
+    let max_depth = check.crawl.max_depth.unwrap_or(DEFAULT_CRAWL_DEPTH);
+    let max_pages = check.crawl.max_pages.unwrap_or(DEFAULT_CRAWL_PAGES);
+
+    let root = match Url::parse(&check.url) {
+        Ok(url) => url,
+        Err(err) => return result.fail(FailureReason::Request, format!("invalid url: {}", err)),
+    };
+    let mut pages = match fetch(client, limiter, root.clone()).await {
+        (url, Ok(Some(html))) => vec![(url, html)],
+        (_, Ok(None)) => return result.fail(FailureReason::Body, "the page is not html".to_string()),
+        (_, Err(err)) => return result.fail(FailureReason::Request, format!("error loading the page: {}", err)),
+    };
+
+    let mut checked: HashMap<Url, Result<(), String>> = HashMap::from([(root, Ok(()))]);
+    let mut broken: BTreeMap<String, Vec<(Url, String)>> = BTreeMap::new();
+    for _ in 0..max_depth {
+        let mut next_pages = Vec::new();
+        for (page, html) in pages {
+            let links = extract_links(&page, &html);
+            let available = max_pages.saturating_sub(checked.len() - 1);
+            let new_links: Vec<Url> = links.iter()
+                .filter(|link| !checked.contains_key(*link))
+                .take(available)
+                .cloned()
+                .collect();
+
+            let fetched: Vec<(Url, Result<Option<String>, String>)> = stream::iter(new_links)
+                .map(|link| fetch(client, limiter, link))
+                .buffer_unordered(CRAWL_CONCURRENCY)
+                .collect()
+                .await;
+            for (link, res) in fetched {
+                checked.insert(link.clone(), res.as_ref().map(|_| ()).map_err(|err| err.clone()));
+                if let Ok(Some(html)) = res {
+                    next_pages.push((link, html));
+                }
+            }
+
+            let page_broken: Vec<(Url, String)> = links.into_iter()
+                .filter_map(|link| match checked.get(&link) {
+                    Some(Err(err)) => Some((link, err.clone())),
+                    _ => None,
+                })
+                .collect();
+            if !page_broken.is_empty() {
+                broken.insert(page.to_string(), page_broken);
+            }
+        }
+        pages = next_pages;
+    }
+    result.latency = Some(start.elapsed());
+
+    if !broken.is_empty() {
+        return result.fail(FailureReason::BrokenLinks, broken_links_error(&broken));
+    }
+
+    println!("Crawl [{}] is OK, {} links validated.", check.url, checked.len() - 1);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use reqwest::Url;
+
+    use super::{broken_links_error, extract_links, Crawl, CrawlCheck};
+
+    #[test]
+    fn extract_links_test() {
+        let page = Url::parse("https://binarycoffee.dev/post/hello").unwrap();
+        let html = r#"
+            <link rel="stylesheet" href="/css/main.css">
+            <img src="images/cover.png" alt="cover">
+            <a href="/users/guille#posts">guille</a>
+            <a HREF='/users/guille'>guille</a>
+            <a href=/about>about</a>
+            <!-- <a href="/commented">commented</a> -->
+            <script>const url = '<a href="/from-script">';</script>
+            <a href="https://github.com/binarycoffee">github</a>
+            <a href="mailto:info@binarycoffee.dev">mail</a>
+            <div data-src="/ignored.png"></div>
+        "#;
+
+        let links: Vec<String> = extract_links(&page, html).iter().map(|link| link.to_string()).collect();
+        assert_eq!(links, vec![
+            "https://binarycoffee.dev/css/main.css",
+            "https://binarycoffee.dev/post/images/cover.png",
+            "https://binarycoffee.dev/users/guille",
+            "https://binarycoffee.dev/about",
+        ]);
+    }
+
+    #[test]
+    fn crawl_check_test() {
+        let check = CrawlCheck::new("https://binarycoffee.dev", &Crawl::default());
+        assert_eq!(check.id(), "CRAWL https://binarycoffee.dev");
+        assert_eq!(check.settings.interval, Some(3600));
+    }
+
+    #[test]
+    fn broken_links_error_test() {
+        let link = |path: &str| Url::parse(&format!("https://binarycoffee.dev{}", path)).unwrap();
+        let broken = BTreeMap::from([
+            ("https://binarycoffee.dev/".to_string(), vec![(link("/old"), "404".to_string())]),
+            ("https://binarycoffee.dev/post".to_string(), vec![
+                (link("/cover.png"), "404".to_string()),
+                (link("/slow"), "timeout".to_string()),
+            ]),
+        ]);
+
+        assert_eq!(
+            broken_links_error(&broken),
+            "3 broken links in 2 pages: https://binarycoffee.dev/ -> https://binarycoffee.dev/old (404); \
+            https://binarycoffee.dev/post -> https://binarycoffee.dev/cover.png (404), https://binarycoffee.dev/slow (timeout)",
+        );
+    }
+}
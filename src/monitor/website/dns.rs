@@ -0,0 +1,248 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use hickory_resolver::TokioAsyncResolver;
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+const DEFAULT_DNS_TIMEOUT_MS: u64 = 5000;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    #[default]
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+}
+
+impl DnsRecordType {
+    fn record_type(&self) -> RecordType {
+        match self {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::Aaaa => RecordType::AAAA,
+            DnsRecordType::Cname => RecordType::CNAME,
+            DnsRecordType::Mx => RecordType::MX,
+            DnsRecordType::Txt => RecordType::TXT,
+        }
+    }
+}
+
+/// Test of the resolution of a domain name.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Dns {
+    pub name: String,
+    /// Type of the record to resolve, `A` by default.
+    pub record_type: Option<DnsRecordType>,
+    /// Nameserver used to resolve the name (`ip` or `ip:port`), the system resolver by default.
+    pub nameserver: Option<String>,
+    /// Values that have to be in the answer, when empty the answer only has to be non-empty.
+    pub expected: Option<Vec<String>>,
+    /// Max time (in milliseconds) to resolve the name.
+    pub timeout: Option<u64>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Dns {
+    pub fn id(&self) -> String {
+        let record_type = self.record_type.unwrap_or_default().record_type();
+        match self.nameserver {
+            Some(ref nameserver) => format!("DNS {} {} @{}", record_type, self.name, nameserver),
+            None => format!("DNS {} {}", record_type, self.name),
+        }
+    }
+
+    fn resolver(&self) -> Result<TokioAsyncResolver, String> {
+        let mut options = ResolverOpts::default();
+        options.timeout = Duration::from_millis(self.timeout.unwrap_or(DEFAULT_DNS_TIMEOUT_MS));
+        options.attempts = 1;
+        options.cache_size = 0;
+
+        match self.nameserver {
+            None => {
+                let (config, _) = hickory_resolver::system_conf::read_system_conf()
+                    .map_err(|err| format!("error reading the system resolver config: {}", err))?;
+                Ok(TokioAsyncResolver::tokio(config, options))
+            }
+            Some(ref nameserver) => {
+                let address = nameserver.parse::<SocketAddr>()
+                    .or_else(|_| nameserver.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                    .map_err(|_| format!("invalid nameserver: {}", nameserver))?;
+                let mut config = ResolverConfig::new();
+                config.add_name_server(NameServerConfig::new(address, Protocol::Udp));
+                options.use_hosts_file = false;
+                Ok(TokioAsyncResolver::tokio(config, options))
+            }
+        }
+    }
+}
+
+/// Text representation of a record, names are compared without the trailing dot and in lowercase.
+fn record_value(data: &RData) -> Option<String> {
+    let name = |name: &hickory_resolver::Name| name.to_utf8().trim_end_matches('.').to_lowercase();
+    match data {
+        RData::A(ip) => Some(ip.to_string()),
+        RData::AAAA(ip) => Some(ip.to_string()),
+        RData::CNAME(cname) => Some(name(cname)),
+        RData::MX(mx) => Some(name(mx.exchange())),
+        RData::TXT(txt) => Some(
+            txt.txt_data().iter().map(|data| String::from_utf8_lossy(data).to_string()).collect(),
+        ),
+        _ => None,
+    }
+}
+
+pub async fn check_dns(dns: &Dns, mut result: CheckResult) -> CheckResult {
+    let resolver = match dns.resolver() {
+        Ok(resolver) => resolver,
+        Err(err) => return result.fail(FailureReason::Dns, err),
+    };
+
+    let record_type = dns.record_type.unwrap_or_default().record_type();
+    let start = Instant::now();
+    let lookup = resolver.lookup(dns.name.as_str(), record_type).await;
+    result.latency = Some(start.elapsed());
+
+    let values: Vec<String> = match lookup {
+        Ok(lookup) => lookup.iter().filter_map(record_value).collect(),
+        Err(err) => match err.kind() {
+            ResolveErrorKind::NoRecordsFound { .. } => Vec::new(),
+            _ => return result.fail(FailureReason::Dns, format!("resolution failed: {}", err)),
+        },
+    };
+
+    let expected = dns.expected.clone().unwrap_or_default();
+    if values.is_empty() {
+        return result.fail(FailureReason::DnsRecord, format!("no {} records found", record_type));
+    }
+    let missing: Vec<&String> = expected.iter()
+        .filter(|value| !values.iter().any(|resolved| resolved.eq_ignore_ascii_case(value.trim_end_matches('.'))))
+        .collect();
+    if !missing.is_empty() {
+        return result.fail(
+            FailureReason::DnsRecord,
+            format!(
+                "expected {} but resolved {}",
+                expected.join(", "),
+                values.join(", "),
+            ),
+        );
+    }
+
+    println!("DNS [{}] is OK.", dns.id());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+
+    use hickory_resolver::proto::op::{Message, MessageType, ResponseCode};
+    use hickory_resolver::proto::rr::rdata::{A, TXT};
+    use hickory_resolver::proto::rr::{Name, RData, Record, RecordType};
+    use tokio::net::UdpSocket;
+
+    use super::{check_dns, Dns, DnsRecordType};
+    use crate::monitor::state::CheckStatus;
+    use crate::monitor::website::models::{CheckKind, CheckResult, FailureReason};
+
+    /// Start a stub nameserver that knows the records of `binarycoffee.test`.
+    async fn start_stub_nameserver() -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            while let Ok((size, peer)) = socket.recv_from(&mut buffer).await {
+                let request = Message::from_vec(&buffer[..size]).unwrap();
+                let query = request.queries()[0].clone();
+                let domain = Name::from_str("binarycoffee.test.").unwrap();
+
+                let mut response = Message::new();
+                response.set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(request.op_code())
+                    .set_recursion_desired(request.recursion_desired())
+                    .set_recursion_available(true)
+                    .add_query(query.clone());
+                if query.name() != &domain {
+                    response.set_response_code(ResponseCode::NXDomain);
+                } else if query.query_type() == RecordType::A {
+                    response.add_answer(Record::from_rdata(
+                        domain.clone(), 60, RData::A(A(Ipv4Addr::new(10, 0, 0, 1))),
+                    ));
+                } else if query.query_type() == RecordType::TXT {
+                    response.add_answer(Record::from_rdata(
+                        domain.clone(), 60, RData::TXT(TXT::new(vec!["v=spf1 -all".to_string()])),
+                    ));
+                }
+                socket.send_to(&response.to_vec().unwrap(), peer).await.unwrap();
+            }
+        });
+        address
+    }
+
+    async fn run(dns: &Dns) -> CheckResult {
+        check_dns(dns, CheckResult::new(dns.id(), CheckKind::Dns, dns.name.clone())).await
+    }
+
+    #[tokio::test]
+    async fn expected_records_test() {
+        let nameserver = start_stub_nameserver().await;
+        let dns = Dns {
+            name: "binarycoffee.test".to_string(),
+            nameserver: Some(nameserver.clone()),
+            expected: Some(vec!["10.0.0.1".to_string()]),
+            ..Default::default()
+        };
+        let result = run(&dns).await;
+        assert_eq!(result.status, CheckStatus::Up, "{:?}", result.error);
+        assert!(result.latency.is_some());
+
+        let dns = Dns { expected: Some(vec!["10.0.0.2".to_string()]), ..dns };
+        let result = run(&dns).await;
+        assert_eq!(result.reason, Some(FailureReason::DnsRecord));
+        assert_eq!(result.error.unwrap(), "expected 10.0.0.2 but resolved 10.0.0.1");
+
+        let dns = Dns {
+            record_type: Some(DnsRecordType::Txt),
+            expected: Some(vec!["v=spf1 -all".to_string()]),
+            ..dns
+        };
+        assert_eq!(run(&dns).await.status, CheckStatus::Up);
+    }
+
+    #[tokio::test]
+    async fn empty_answer_test() {
+        let nameserver = start_stub_nameserver().await;
+        let dns = Dns {
+            name: "binarycoffee.test".to_string(),
+            record_type: Some(DnsRecordType::Mx),
+            nameserver: Some(nameserver.clone()),
+            ..Default::default()
+        };
+        assert_eq!(run(&dns).await.reason, Some(FailureReason::DnsRecord));
+
+        let dns = Dns { name: "unknown.test".to_string(), record_type: None, ..dns };
+        let result = run(&dns).await;
+        assert_eq!(result.status, CheckStatus::Down);
+        assert_eq!(result.reason, Some(FailureReason::DnsRecord));
+    }
+
+    #[test]
+    fn deserialize_test() {
+        let dns: Dns = serde_json::from_str(
+            r#"{"name": "binarycoffee.dev", "record_type": "AAAA", "nameserver": "1.1.1.1", "interval": 60}"#
+        ).unwrap();
+        assert_eq!(dns.record_type, Some(DnsRecordType::Aaaa));
+        assert_eq!(dns.settings.interval, Some(60));
+        assert_eq!(dns.id(), "DNS AAAA binarycoffee.dev @1.1.1.1");
+    }
+}
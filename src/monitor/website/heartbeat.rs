@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::utils::format_duration;
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+/// Job that has to check in through the API (`POST /heartbeat/{name}`) at least every `period`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Heartbeat {
+    pub name: String,
+    /// Expected seconds between pings.
+    pub period: u64,
+    /// Extra seconds to wait for a late ping before the alert.
+    pub grace: Option<u64>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Heartbeat {
+    pub fn id(&self) -> String {
+        format!("HEARTBEAT {}", self.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ping {
+    Success,
+    /// The job started, used to measure the duration of the job.
+    Start,
+    /// The job reported a failure.
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+struct HeartbeatState {
+    /// Moment since the pings are expected, the start of the service or the last successful ping.
+    last_success: SystemTime,
+    started: Option<SystemTime>,
+    failed: bool,
+    duration: Option<Duration>,
+}
+
+/// Last pings of the heartbeats, shared between the API that receives them and the checks.
+#[derive(Clone)]
+pub struct HeartbeatRegistry {
+    states: Arc<Mutex<HashMap<String, HeartbeatState>>>,
+}
+
+impl HeartbeatRegistry {
+    pub fn new(heartbeats: &[Heartbeat], now: SystemTime) -> HeartbeatRegistry {
+        let states = heartbeats.iter()
+            .map(|heartbeat| (heartbeat.name.clone(), HeartbeatState {
+                last_success: now,
+                started: None,
+                failed: false,
+                duration: None,
+            }))
+            .collect();
+        HeartbeatRegistry { states: Arc::new(Mutex::new(states)) }
+    }
+
+    /// Register a ping of the heartbeat, fails when the heartbeat isn't configured.
+    pub fn ping(&self, name: &str, ping: Ping, now: SystemTime) -> Result<(), String> {
+        let mut states = self.states.lock().unwrap();
+        let state = states.get_mut(name).ok_or(format!("heartbeat {} is not configured", name))?;
+        match ping {
+            Ping::Start => state.started = Some(now),
+            Ping::Fail => {
+                state.failed = true;
+                state.started = None;
+            }
+            Ping::Success => {
+                state.duration = state.started.take()
+                    .map(|started| now.duration_since(started).unwrap_or_default());
+                state.last_success = now;
+                state.failed = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that the last ping arrived in time, the duration of the last run is the latency.
+    pub fn check(&self, heartbeat: &Heartbeat, mut result: CheckResult, now: SystemTime) -> CheckResult {
+        let Some(state) = self.states.lock().unwrap().get(&heartbeat.name).cloned() else {
+            return result.fail(FailureReason::MissedHeartbeat, "heartbeat is not registered".to_string());
+        };
+        result.latency = state.duration;
+
+        if state.failed {
+            return result.fail(FailureReason::HeartbeatFailed, "the job reported a failure".to_string());
+        }
+        let elapsed = now.duration_since(state.last_success).unwrap_or_default();
+        let max_elapsed = Duration::from_secs(heartbeat.period + heartbeat.grace.unwrap_or(0));
+        if elapsed > max_elapsed {
+            return result.fail(
+                FailureReason::MissedHeartbeat,
+                format!(
+                    "no ping received in {} (expected every {})",
+                    format_duration(elapsed),
+                    format_duration(Duration::from_secs(heartbeat.period)),
+                ),
+            );
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Heartbeat, HeartbeatRegistry, Ping};
+    use crate::monitor::state::CheckStatus;
+    use crate::monitor::website::models::{CheckKind, CheckResult, FailureReason};
+
+    fn backup() -> Heartbeat {
+        Heartbeat { name: "backup".to_string(), period: 3600, grace: Some(300), ..Default::default() }
+    }
+
+    fn check(registry: &HeartbeatRegistry, now: SystemTime) -> CheckResult {
+        let heartbeat = backup();
+        let result = CheckResult::new(heartbeat.id(), CheckKind::Heartbeat, heartbeat.name.clone());
+        registry.check(&heartbeat, result, now)
+    }
+
+    #[test]
+    fn missed_heartbeat_test() {
+        let start = SystemTime::now();
+        let registry = HeartbeatRegistry::new(&[backup()], start);
+        let minutes = |minutes: u64| start + Duration::from_secs(minutes * 60);
+
+        assert_eq!(check(&registry, minutes(64)).status, CheckStatus::Up);
+        let missed = check(&registry, minutes(66));
+        assert_eq!(missed.reason, Some(FailureReason::MissedHeartbeat));
+        assert_eq!(missed.error.unwrap(), "no ping received in 1h 6m (expected every 1h 0m)");
+
+        registry.ping("backup", Ping::Success, minutes(70)).unwrap();
+        assert_eq!(check(&registry, minutes(71)).status, CheckStatus::Up);
+        assert!(registry.ping("unknown", Ping::Success, minutes(70)).is_err());
+    }
+
+    #[test]
+    fn job_duration_and_failure_test() {
+        let start = SystemTime::now();
+        let registry = HeartbeatRegistry::new(&[backup()], start);
+
+        registry.ping("backup", Ping::Start, start).unwrap();
+        registry.ping("backup", Ping::Success, start + Duration::from_secs(90)).unwrap();
+        assert_eq!(check(&registry, start + Duration::from_secs(100)).latency, Some(Duration::from_secs(90)));
+
+        registry.ping("backup", Ping::Fail, start + Duration::from_secs(200)).unwrap();
+        let failed = check(&registry, start + Duration::from_secs(210));
+        assert_eq!(failed.reason, Some(FailureReason::HeartbeatFailed));
+
+        registry.ping("backup", Ping::Success, start + Duration::from_secs(300)).unwrap();
+        assert_eq!(check(&registry, start + Duration::from_secs(310)).status, CheckStatus::Up);
+    }
+}
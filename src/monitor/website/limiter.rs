@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use reqwest::Url;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bound the number of checks executed at the same time, globally and for every host.
+pub struct ConcurrencyLimiter {
+    global: Arc<Semaphore>,
+    per_host_limit: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// Permits of a running check, they are released when the value is dropped.
+pub struct CheckPermit {
+    _host: OwnedSemaphorePermit,
+    _global: OwnedSemaphorePermit,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(global_limit: usize, per_host_limit: usize) -> ConcurrencyLimiter {
+        ConcurrencyLimiter {
+            global: Arc::new(Semaphore::new(global_limit.max(1))),
+            per_host_limit: per_host_limit.max(1),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until the check can be executed. The host permit is taken first, so a check waiting for a
+    /// busy host doesn't block the checks of the other hosts.
+    pub async fn acquire(&self, host: &str) -> CheckPermit {
+        let host_semaphore = self.hosts.lock().unwrap()
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_host_limit)))
+            .clone();
+
+        let host_permit = host_semaphore.acquire_owned().await.expect("Host semaphore closed.");
+        let global_permit = self.global.clone().acquire_owned().await.expect("Global semaphore closed.");
+        CheckPermit { _host: host_permit, _global: global_permit }
+    }
+
+    /// Wait until a request to the host of the url can be sent.
+    pub async fn acquire_url(&self, url: &Url) -> CheckPermit {
+        self.acquire(url.host_str().unwrap_or_default()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::ConcurrencyLimiter;
+
+    #[tokio::test]
+    async fn per_host_limit_test() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(10, 1));
+
+        let _permit = limiter.acquire("binarycoffee.dev").await;
+        // other hosts are not affected by a busy host
+        assert!(timeout(Duration::from_millis(50), limiter.acquire("api.binarycoffee.dev")).await.is_ok());
+        // the same host has to wait
+        assert!(timeout(Duration::from_millis(50), limiter.acquire("binarycoffee.dev")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn global_limit_test() {
+        let limiter = ConcurrencyLimiter::new(1, 5);
+
+        let permit = limiter.acquire("binarycoffee.dev").await;
+        assert!(timeout(Duration::from_millis(50), limiter.acquire("api.binarycoffee.dev")).await.is_err());
+        drop(permit);
+        assert!(timeout(Duration::from_millis(50), limiter.acquire("api.binarycoffee.dev")).await.is_ok());
+    }
+}
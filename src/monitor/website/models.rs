@@ -0,0 +1,182 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::state::CheckStatus;
+use crate::monitor::website::command::PerfData;
+use crate::monitor::website::ssl::CertificateInfo;
+
+/// Importance of the alerts of a check, used to route them.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Critical,
+}
+
+/// Settings shared by all the checks, they are defined in the same json object of the check.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct CheckSettings {
+    /// Seconds between two executions of the check, by default `website_monitor_timeout`.
+    pub interval: Option<u64>,
+    /// Max seconds to randomly delay the first execution of the check.
+    pub jitter: Option<u64>,
+    /// Consecutive failures before the check is notified as DOWN (by default 1).
+    pub fail_threshold: Option<u32>,
+    /// Consecutive successes before a failing check is notified as UP (by default 1).
+    pub recover_threshold: Option<u32>,
+    /// Ids of the checks this check depends on, its failures are reported as UNREACHABLE while one
+    /// of them is down.
+    pub depends_on: Option<Vec<String>>,
+    /// Labels of the check (team, component, ...) used to route its alerts.
+    pub tags: Option<Vec<String>>,
+    /// Severity of the alerts of the check (by default critical).
+    pub severity: Option<Severity>,
+    /// Name of the escalation policy of the DOWN alerts of the check, it has priority over the policy
+    /// of the routing rules.
+    pub escalation: Option<String>,
+}
+
+impl CheckSettings {
+    pub fn severity(&self) -> Severity {
+        self.severity.unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckKind {
+    Api,
+    Frontend,
+    Certificate,
+    Tcp,
+    Dns,
+    Scenario,
+    Sitemap,
+    Crawl,
+    Heartbeat,
+    Command,
+}
+
+/// Cause of the failure of a check.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    /// The request couldn't be done (connection error, timeout, ...).
+    Request,
+    Status,
+    Body,
+    Json,
+    Latency,
+    /// The certificate is close to its expiration.
+    CertificateExpiry,
+    CertificateExpired,
+    CertificateNotYetValid,
+    HostnameMismatch,
+    /// The certificate is not signed by a trusted authority, or the chain is incomplete.
+    UntrustedChain,
+    SelfSignedCertificate,
+    TlsHandshake,
+    /// The service didn't answer the expected data.
+    Banner,
+    /// The name couldn't be resolved (nameserver unreachable, timeout, ...).
+    Dns,
+    /// The resolved records are empty or don't match the expected values.
+    DnsRecord,
+    /// A value couldn't be captured from the response of a step, or a variable wasn't captured.
+    Capture,
+    /// Some pages of a sitemap don't load.
+    BrokenPages,
+    /// Some links or assets of a page are broken.
+    BrokenLinks,
+    /// The body of the response is different from the baseline.
+    ContentChanged,
+    /// The ping of a heartbeat didn't arrive in time.
+    MissedHeartbeat,
+    /// The job of a heartbeat reported a failure.
+    HeartbeatFailed,
+    /// The command returned a WARNING, CRITICAL or UNKNOWN exit code, or couldn't be executed.
+    Command,
+}
+
+/// Time spent in the phases of an http request, the http client doesn't expose the time of the dns
+/// resolution, the connection and the TLS handshake.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    /// Time to first byte, until the headers of the response are received.
+    pub ttfb: Option<Duration>,
+    pub total: Option<Duration>,
+}
+
+/// Result of one execution of a check, it's the raw information used by the notifications (and any
+/// other consumer) to report the status of the monitored services.
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    pub check_id: String,
+    pub kind: CheckKind,
+    pub target: String,
+    pub status: CheckStatus,
+    pub http_code: Option<u16>,
+    pub latency: Option<Duration>,
+    pub timings: Option<Timings>,
+    pub certificate: Option<CertificateInfo>,
+    pub reason: Option<FailureReason>,
+    pub error: Option<String>,
+    /// Excerpt of the unified diff when the content changed.
+    pub diff: Option<String>,
+    /// First line of the output of a command, without the performance data.
+    pub output: Option<String>,
+    pub perfdata: Option<Vec<PerfData>>,
+    /// Number of requests sent, including the retries.
+    pub attempts: Option<u32>,
+    pub timestamp: SystemTime,
+}
+
+impl CheckResult {
+    pub fn new(check_id: String, kind: CheckKind, target: String) -> CheckResult {
+        CheckResult {
+            check_id,
+            kind,
+            target,
+            status: CheckStatus::Up,
+            http_code: None,
+            latency: None,
+            timings: None,
+            certificate: None,
+            reason: None,
+            error: None,
+            diff: None,
+            output: None,
+            perfdata: None,
+            attempts: None,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    pub fn fail(mut self, reason: FailureReason, error: String) -> CheckResult {
+        self.status = CheckStatus::Down;
+        self.reason = Some(reason);
+        self.error = Some(error);
+        self
+    }
+
+    pub fn degrade(mut self, reason: FailureReason, error: String) -> CheckResult {
+        self.status = CheckStatus::Degraded;
+        self.reason = Some(reason);
+        self.error = Some(error);
+        self
+    }
+
+    /// The check failed because the check it depends on is down.
+    pub fn unreachable(mut self, parent: &str) -> CheckResult {
+        self.status = CheckStatus::Unreachable;
+        self.error = Some(format!("depends on {}, which is down", parent));
+        self
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == CheckStatus::Up
+    }
+}
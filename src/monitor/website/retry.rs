@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 10000;
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MS);
+const DEFAULT_BACKOFF_DELAY_MS: u64 = 1000;
+const DEFAULT_BACKOFF_MAX_DELAY_MS: u64 = 60000;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffStrategy {
+    #[default]
+    Fixed,
+    /// The delay is doubled after every attempt.
+    Exponential,
+}
+
+/// Wait between the attempts of a request.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Backoff {
+    pub strategy: Option<BackoffStrategy>,
+    /// Milliseconds to wait after the first failed attempt.
+    pub delay: Option<u64>,
+    /// Max milliseconds to wait with the exponential strategy.
+    pub max_delay: Option<u64>,
+    /// Max random milliseconds added to every delay.
+    pub jitter: Option<u64>,
+}
+
+impl Backoff {
+    /// Time to wait after the given number of failed attempts, without the jitter.
+    pub fn base_delay(&self, failed_attempts: u32) -> Duration {
+        let delay = self.delay.unwrap_or(DEFAULT_BACKOFF_DELAY_MS);
+        let delay = match self.strategy.unwrap_or_default() {
+            BackoffStrategy::Fixed => delay,
+            BackoffStrategy::Exponential => {
+                let factor = 2u64.saturating_pow(failed_attempts.saturating_sub(1));
+                delay.saturating_mul(factor).min(self.max_delay.unwrap_or(DEFAULT_BACKOFF_MAX_DELAY_MS))
+            }
+        };
+        Duration::from_millis(delay)
+    }
+
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        let jitter = match self.jitter {
+            Some(jitter) if jitter > 0 => rand::thread_rng().gen_range(0..=jitter),
+            _ => 0,
+        };
+        self.base_delay(failed_attempts) + Duration::from_millis(jitter)
+    }
+}
+
+/// Timeout and retries of the requests of an http test.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RetryPolicy {
+    /// Max time (in milliseconds) of every attempt.
+    pub timeout: Option<u64>,
+    /// Attempts after the first one, by default `times_to_retry - 1`.
+    pub retries: Option<u32>,
+    /// Retry the responses with an unexpected status, by default only the failed requests are retried.
+    pub retry_on_status: Option<bool>,
+    pub backoff: Option<Backoff>,
+}
+
+impl RetryPolicy {
+    pub fn timeout(&self) -> Duration {
+        self.timeout.map(Duration::from_millis).unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Total number of attempts, `times_to_retry` is the global number of attempts.
+    pub fn attempts(&self, times_to_retry: i64) -> u32 {
+        let default_retries = u32::try_from(times_to_retry.saturating_sub(1)).unwrap_or(0);
+        self.retries.unwrap_or(default_retries) + 1
+    }
+
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        self.backoff.clone().unwrap_or_default().delay(failed_attempts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Backoff, BackoffStrategy, RetryPolicy};
+
+    #[test]
+    fn backoff_test() {
+        let fixed = Backoff { delay: Some(500), ..Default::default() };
+        assert_eq!(fixed.base_delay(1), Duration::from_millis(500));
+        assert_eq!(fixed.base_delay(4), Duration::from_millis(500));
+
+        let exponential = Backoff {
+            strategy: Some(BackoffStrategy::Exponential),
+            delay: Some(500),
+            max_delay: Some(3000),
+            jitter: Some(100),
+        };
+        assert_eq!(exponential.base_delay(1), Duration::from_millis(500));
+        assert_eq!(exponential.base_delay(3), Duration::from_millis(2000));
+        assert_eq!(exponential.base_delay(40), Duration::from_millis(3000));
+
+        let delay = exponential.delay(2);
+        assert!(delay >= Duration::from_millis(1000) && delay <= Duration::from_millis(1100));
+    }
+
+    #[test]
+    fn retry_policy_test() {
+        let policy: RetryPolicy = serde_json::from_str(
+            r#"{"timeout": 2000, "backoff": {"strategy": "exponential", "delay": 200}}"#
+        ).unwrap();
+
+        assert_eq!(policy.timeout(), Duration::from_secs(2));
+        assert_eq!(policy.attempts(5), 5);
+        assert_eq!(policy.attempts(0), 1);
+        assert_eq!(RetryPolicy { retries: Some(0), ..policy.clone() }.attempts(5), 1);
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(RetryPolicy::default().delay(2), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::monitor::website::assertions::{json_path_to_pointer, ResponseAssertions};
+use crate::monitor::website::auth::Auth;
+use crate::monitor::website::models::CheckSettings;
+
+/// Ordered list of http requests, the values captured from a response can be used in the next
+/// steps with the format `{{variable}}`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<Step>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Scenario {
+    pub fn id(&self) -> String {
+        format!("SCENARIO {}", self.name)
+    }
+
+    pub fn url(&self) -> &str {
+        self.steps.first().map(|step| step.url.as_str()).unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Step {
+    pub url: String,
+    /// Method of the request, `GET` by default.
+    pub method: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+    pub body: Option<String>,
+    pub auth: Option<Auth>,
+    /// Values to capture from the response, by name of the variable.
+    pub capture: Option<HashMap<String, Capture>>,
+    #[serde(flatten)]
+    pub assertions: ResponseAssertions,
+}
+
+impl Step {
+    pub fn method(&self) -> String {
+        self.method.clone().unwrap_or("GET".to_string()).to_uppercase()
+    }
+
+    /// Copy of the step with the variables replaced in the url, headers, body and authentication.
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<Step, String> {
+        let headers = match self.headers {
+            Some(ref headers) => Some(
+                headers.iter()
+                    .map(|(name, value)| Ok((name.clone(), render(value, variables)?)))
+                    .collect::<Result<HashMap<String, String>, String>>()?,
+            ),
+            None => None,
+        };
+        let auth = match self.auth {
+            Some(Auth::Basic { ref username, ref password }) => Some(Auth::Basic {
+                username: render(username, variables)?,
+                password: render(password, variables)?,
+            }),
+            Some(Auth::Bearer { ref token }) => Some(Auth::Bearer { token: render(token, variables)? }),
+            Some(Auth::Header { ref name, ref value }) => Some(Auth::Header {
+                name: name.clone(),
+                value: render(value, variables)?,
+            }),
+            None => None,
+        };
+        Ok(Step {
+            url: render(&self.url, variables)?,
+            headers,
+            body: self.body.as_ref().map(|body| render(body, variables)).transpose()?,
+            auth,
+            ..self.clone()
+        })
+    }
+}
+
+/// Source of a captured value.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "from", rename_all = "lowercase")]
+pub enum Capture {
+    Json { path: String },
+    Header { name: String },
+    Cookie { name: String },
+    /// The group `1` is captured by default, the group `0` is the whole match.
+    Regex { pattern: String, group: Option<usize> },
+}
+
+impl Capture {
+    pub fn extract(&self, headers: &HeaderMap, body: &[u8]) -> Result<String, String> {
+        match self {
+            Capture::Json { path } => {
+                let json: Value = serde_json::from_slice(body)
+                    .map_err(|err| format!("body is not a valid json: {}", err))?;
+                match json.pointer(&json_path_to_pointer(path)?) {
+                    Some(Value::String(value)) => Ok(value.clone()),
+                    Some(Value::Null) | None => Err(format!("{} not found in the body", path)),
+                    Some(value) => Ok(value.to_string()),
+                }
+            }
+            Capture::Header { name } => headers.get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+                .ok_or(format!("header {} not found", name)),
+            Capture::Cookie { name } => headers.get_all(SET_COOKIE).iter()
+                .filter_map(|value| value.to_str().ok())
+                .filter_map(|cookie| cookie.split(';').next()?.split_once('='))
+                .find(|(cookie, _)| cookie.trim() == name)
+                .map(|(_, value)| value.trim().to_string())
+                .ok_or(format!("cookie {} not found", name)),
+            Capture::Regex { pattern, group } => {
+                let regex = Regex::new(pattern).map_err(|err| format!("invalid regex {}: {}", pattern, err))?;
+                let body = String::from_utf8_lossy(body);
+                regex.captures(&body)
+                    .and_then(|captures| captures.get(group.unwrap_or(1)))
+                    .map(|value| value.as_str().to_string())
+                    .ok_or(format!("regex {} doesn't match the body", pattern))
+            }
+        }
+    }
+}
+
+/// Replace the references to variables (`{{name}}`) with their values.
+pub fn render(template: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")
+            .ok_or(format!("unclosed variable in \"{}\"", template))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = variables.get(name).ok_or(format!("variable {} is not captured", name))?;
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+
+    use super::{render, Capture};
+
+    #[test]
+    fn render_test() {
+        let variables = HashMap::from([("token".to_string(), "abc".to_string())]);
+
+        assert_eq!(render("Bearer {{token}}", &variables), Ok("Bearer abc".to_string()));
+        assert_eq!(render("/users/{{ token }}/posts", &variables), Ok("/users/abc/posts".to_string()));
+        assert_eq!(render("{{user}}", &variables), Err("variable user is not captured".to_string()));
+        assert!(render("{{token", &variables).is_err());
+    }
+
+    #[test]
+    fn capture_test() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Request-Id", HeaderValue::from_static("42"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("theme=dark; Path=/"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("session=s3cr3t; HttpOnly"));
+        let body = br#"{"data": {"token": "abc", "id": 7}, "csrf": "<input value='xyz'>"}"#;
+
+        let capture = |capture: Capture| capture.extract(&headers, body);
+        assert_eq!(capture(Capture::Json { path: "$.data.token".to_string() }), Ok("abc".to_string()));
+        assert_eq!(capture(Capture::Json { path: "$.data.id".to_string() }), Ok("7".to_string()));
+        assert!(capture(Capture::Json { path: "$.data.user".to_string() }).is_err());
+        assert_eq!(capture(Capture::Header { name: "x-request-id".to_string() }), Ok("42".to_string()));
+        assert_eq!(capture(Capture::Cookie { name: "session".to_string() }), Ok("s3cr3t".to_string()));
+        assert_eq!(
+            capture(Capture::Regex { pattern: "value='(\\w+)'".to_string(), group: None }),
+            Ok("xyz".to_string()),
+        );
+    }
+}
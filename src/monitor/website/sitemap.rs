@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use futures_util::stream::{self, StreamExt};
+use rand::seq::SliceRandom;
+use regex::Regex;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::monitor::website::limiter::ConcurrencyLimiter;
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+const DEFAULT_SITEMAP_CONCURRENCY: usize = 5;
+const DEFAULT_PAGE_TIMEOUT_MS: u64 = 10000;
+/// Max number of broken pages listed in the alert.
+const MAX_LISTED_PAGES: usize = 10;
+
+/// Test of every page published in a sitemap, in XML format or a JSON list of urls.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Sitemap {
+    pub url: String,
+    /// Number of random pages to validate in every execution, all the pages by default.
+    pub sample: Option<usize>,
+    /// Max number of pages validated at the same time.
+    pub max_concurrency: Option<usize>,
+    /// Max time (in milliseconds) to load every page.
+    pub timeout: Option<u64>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Sitemap {
+    pub fn id(&self) -> String {
+        format!("SITEMAP {}", self.url)
+    }
+}
+
+/// Extract the urls of the sitemap, relative urls are resolved from the url of the sitemap. The JSON
+/// lists can contain the urls, or objects with the url in the `url`, `loc` or `path` field.
+pub fn parse_sitemap(base: &Url, body: &str) -> Result<Vec<Url>, String> {
+    let links: Vec<String> = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(items)) => items.iter()
+            .filter_map(|item| match item {
+                Value::String(url) => Some(url.clone()),
+                Value::Object(fields) => ["url", "loc", "path"].iter()
+                    .find_map(|field| fields.get(*field)?.as_str().map(|url| url.to_string())),
+                _ => None,
+            })
+            .collect(),
+        Ok(_) => return Err("the json sitemap should be a list of urls".to_string()),
+        Err(_) => {
+            let loc = Regex::new(r"<loc>\s*(.*?)\s*</loc>").unwrap();
+            loc.captures_iter(body)
+                .map(|captures| captures[1].replace("&amp;", "&"))
+                .collect()
+        }
+    };
+
+    if links.is_empty() {
+        return Err("the sitemap doesn't contain urls".to_string());
+    }
+    links.iter()
+        .map(|link| base.join(link).map_err(|err| format!("invalid url {}: {}", link, err)))
+        .collect()
+}
+
+/// Summary of the broken pages, only the first ones are listed to keep the alert short.
+fn broken_pages_error(broken: &[(Url, String)], total: usize) -> String {
+    let mut pages: Vec<String> = broken.iter()
+        .take(MAX_LISTED_PAGES)
+        .map(|(url, err)| format!("{} ({})", url, err))
+        .collect();
+    if broken.len() > MAX_LISTED_PAGES {
+        pages.push(format!("and {} more", broken.len() - MAX_LISTED_PAGES));
+    }
+    format!("{} of {} pages are broken: {}", broken.len(), total, pages.join(", "))
+}
+
+async fn check_page(client: &Client, limiter: &ConcurrencyLimiter, url: Url, timeout: Duration) -> Result<(), (Url, String)> {
+    let _permit = limiter.acquire_url(&url).await;
+    match client.get(url.clone()).timeout(timeout).send().await {
+        Ok(res) if res.status().is_success() => Ok(()),
+        Ok(res) => Err((url, res.status().as_u16().to_string())),
+        Err(err) if err.is_timeout() => Err((url, "timeout".to_string())),
+        Err(_) => Err((url, "request failed".to_string())),
+    }
+}
+
+/// Validate the pages of the sitemap, every request takes a permit of the limiter of its host.
+pub async fn check_sitemap(sitemap: &Sitemap, client: &Client, limiter: &ConcurrencyLimiter, mut result: CheckResult) -> CheckResult {
+    let start = Instant::now();
+    let timeout = Duration::from_millis(sitemap.timeout.unwrap_or(DEFAULT_PAGE_TIMEOUT_MS));
+
+    let base = match Url::parse(&sitemap.url) {
+        Ok(url) => url,
+        Err(err) => return result.fail(FailureReason::Request, format!("invalid url: {}", err)),
+    };
+    let permit = limiter.acquire_url(&base).await;
+    let res = match client.get(base.clone()).timeout(timeout).send().await {
+        Ok(res) => res,
+        Err(err) => return result.fail(FailureReason::Request, err.to_string()),
+    };
+    result.http_code = Some(res.status().as_u16());
+    if !res.status().is_success() {
+        return result.fail(FailureReason::Status, format!("unexpected status {}", res.status()));
+    }
+    let body = match res.text().await {
+        Ok(body) => body,
+        Err(err) => return result.fail(FailureReason::Body, format!("error reading the body: {}", err)),
+    };
+    drop(permit);
+    let mut pages = match parse_sitemap(&base, &body) {
+        Ok(pages) => pages,
+        Err(err) => return result.fail(FailureReason::Body, err),
+    };
+
+    if let Some(sample) = sitemap.sample {
+        pages.shuffle(&mut rand::thread_rng());
+        pages.truncate(sample);
+    }
+    let total = pages.len();
+    let concurrency = sitemap.max_concurrency.unwrap_or(DEFAULT_SITEMAP_CONCURRENCY).max(1);
+    let mut broken: Vec<(Url, String)> = stream::iter(pages)
+        .map(|url| check_page(client, limiter, url, timeout))
+        .buffer_unordered(concurrency)
+        .filter_map(|page| async move { page.err() })
+        .collect()
+        .await;
+    result.latency = Some(start.elapsed());
+
+    if !broken.is_empty() {
+        broken.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        return result.fail(FailureReason::BrokenPages, broken_pages_error(&broken, total));
+    }
+
+    println!("Sitemap [{}] is OK, {} pages validated.", sitemap.url, total);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::{broken_pages_error, parse_sitemap};
+
+    #[test]
+    fn parse_xml_sitemap_test() {
+        let base = Url::parse("https://binarycoffee.dev/sitemap.xml").unwrap();
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://binarycoffee.dev/</loc></url>
+              <url>
+                <loc> https://binarycoffee.dev/post?page=1&amp;tag=rust </loc>
+              </url>
+            </urlset>"#;
+
+        let pages = parse_sitemap(&base, body).unwrap();
+        assert_eq!(pages[0].as_str(), "https://binarycoffee.dev/");
+        assert_eq!(pages[1].as_str(), "https://binarycoffee.dev/post?page=1&tag=rust");
+    }
+
+    #[test]
+    fn parse_json_sitemap_test() {
+        let base = Url::parse("https://api.binarycoffee.dev/api/sitemap").unwrap();
+
+        let pages = parse_sitemap(&base, r#"["https://binarycoffee.dev/", "/post/hello"]"#).unwrap();
+        assert_eq!(pages[1].as_str(), "https://api.binarycoffee.dev/post/hello");
+
+        let pages = parse_sitemap(&base, r#"[{"url": "https://binarycoffee.dev/users/guille"}]"#).unwrap();
+        assert_eq!(pages[0].as_str(), "https://binarycoffee.dev/users/guille");
+
+        assert!(parse_sitemap(&base, "[]").is_err());
+        assert!(parse_sitemap(&base, r#"{"urls": []}"#).is_err());
+    }
+
+    #[test]
+    fn broken_pages_error_test() {
+        let broken: Vec<(Url, String)> = (0..12)
+            .map(|page| (Url::parse(&format!("https://binarycoffee.dev/{}", page)).unwrap(), "404".to_string()))
+            .collect();
+
+        let error = broken_pages_error(&broken[..1], 20);
+        assert_eq!(error, "1 of 20 pages are broken: https://binarycoffee.dev/0 (404)");
+        let error = broken_pages_error(&broken, 20);
+        assert!(error.starts_with("12 of 20 pages are broken: "));
+        assert!(error.ends_with("https://binarycoffee.dev/9 (404), and 2 more"));
+    }
+}
@@ -0,0 +1,286 @@
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use openssl::asn1::Asn1Time;
+use openssl::nid::Nid;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use openssl::x509::{X509Ref, X509VerifyResult};
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+/// Days before the expiration of a certificate to notify a warning, when they are not configured.
+pub const DEFAULT_EXPIRY_WARNING_DAYS: [u64; 4] = [30, 14, 7, 1];
+
+const DEFAULT_TLS_PORT: u16 = 443;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// codes of the certificate verification errors of openssl (`X509_V_ERR_*`)
+const UNABLE_TO_GET_ISSUER_CERT: i32 = 2;
+const CERT_NOT_YET_VALID: i32 = 9;
+const CERT_HAS_EXPIRED: i32 = 10;
+const DEPTH_ZERO_SELF_SIGNED_CERT: i32 = 18;
+const SELF_SIGNED_CERT_IN_CHAIN: i32 = 19;
+const UNABLE_TO_GET_ISSUER_CERT_LOCALLY: i32 = 20;
+const UNABLE_TO_VERIFY_LEAF_SIGNATURE: i32 = 21;
+const HOSTNAME_MISMATCH: i32 = 62;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Ssl {
+    pub url: String,
+    /// Port of the TLS service, by default 443.
+    pub port: Option<u16>,
+    /// Server name sent in the handshake (SNI) and used to validate the certificate, by default the
+    /// `url`.
+    pub sni: Option<String>,
+    /// Days before the expiration of the certificate to notify a warning, every threshold is notified
+    /// once.
+    pub expiry_warning_days: Option<Vec<u64>>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Ssl {
+    pub fn id(&self) -> String {
+        match self.port {
+            Some(port) if port != DEFAULT_TLS_PORT => format!("SSL {}:{}", self.url, port),
+            _ => format!("SSL {}", self.url),
+        }
+    }
+}
+
+/// Information of the certificate of a domain.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CertificateInfo {
+    pub days_remaining: i64,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    /// Negotiated protocol, ex: `TLSv1.3`.
+    pub tls_version: String,
+    /// Lowest warning threshold (in days) reached by the certificate.
+    pub warning_threshold: Option<u64>,
+}
+
+/// Lowest threshold that is greater or equal than the days remaining to the expiration.
+pub fn warning_threshold(days_remaining: i64, thresholds: &[u64]) -> Option<u64> {
+    thresholds.iter()
+        .filter(|threshold| days_remaining <= **threshold as i64)
+        .min()
+        .copied()
+}
+
+/// Reason and description of a failure of the check.
+type Failure = (FailureReason, String);
+
+/// Failure reason and description of an error of the certificate verification.
+fn verify_error(verify: X509VerifyResult, server_name: &str, cert: &CertificateInfo) -> Failure {
+    match verify.as_raw() {
+        CERT_HAS_EXPIRED => (
+            FailureReason::CertificateExpired,
+            format!("certificate expired {} days ago", -cert.days_remaining),
+        ),
+        CERT_NOT_YET_VALID => (FailureReason::CertificateNotYetValid, "certificate is not valid yet".to_string()),
+        HOSTNAME_MISMATCH => (
+            FailureReason::HostnameMismatch,
+            format!("certificate is not valid for {} (SANs: {})", server_name, cert.sans.join(", ")),
+        ),
+        DEPTH_ZERO_SELF_SIGNED_CERT | SELF_SIGNED_CERT_IN_CHAIN => (
+            FailureReason::SelfSignedCertificate,
+            format!("self-signed certificate ({})", verify.error_string()),
+        ),
+        UNABLE_TO_GET_ISSUER_CERT | UNABLE_TO_GET_ISSUER_CERT_LOCALLY | UNABLE_TO_VERIFY_LEAF_SIGNATURE => (
+            FailureReason::UntrustedChain,
+            format!("incomplete certificate chain ({})", verify.error_string()),
+        ),
+        _ => (
+            FailureReason::UntrustedChain,
+            format!("untrusted certificate ({})", verify.error_string()),
+        ),
+    }
+}
+
+fn certificate_info(cert: &X509Ref, tls_version: &str) -> Result<CertificateInfo, String> {
+    let now = Asn1Time::days_from_now(0).map_err(|err| err.to_string())?;
+    let diff = now.diff(cert.not_after()).map_err(|err| err.to_string())?;
+    let issuer = cert.issuer_name().entries_by_nid(Nid::COMMONNAME).next()
+        .or_else(|| cert.issuer_name().entries_by_nid(Nid::ORGANIZATIONNAME).next())
+        .and_then(|entry| entry.data().as_utf8().ok())
+        .map(|name| name.to_string())
+        .unwrap_or_default();
+    let sans = cert.subject_alt_names()
+        .map(|names| names.iter().filter_map(|name| name.dnsname().map(|dns| dns.to_string())).collect())
+        .unwrap_or_default();
+
+    Ok(CertificateInfo {
+        days_remaining: diff.days as i64,
+        issuer,
+        sans,
+        tls_version: tls_version.to_string(),
+        warning_threshold: None,
+    })
+}
+
+/// Connect to the TLS service and diagnose its certificate. The handshake is done without aborting
+/// on verification errors, so the certificate can be inspected to report the exact problem.
+fn probe(ssl: &Ssl) -> Result<(CertificateInfo, Option<Failure>), Failure> {
+    let port = ssl.port.unwrap_or(DEFAULT_TLS_PORT);
+    let server_name = ssl.sni.clone().unwrap_or(ssl.url.clone());
+    let request_error = |err: String| (FailureReason::Request, err);
+
+    let addr = (ssl.url.as_str(), port).to_socket_addrs()
+        .map_err(|err| request_error(format!("error resolving {}: {}", ssl.url, err)))?
+        .next()
+        .ok_or_else(|| request_error(format!("{} has no addresses", ssl.url)))?;
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+        .map_err(|err| request_error(format!("error connecting to {}: {}", addr, err)))?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT)).map_err(|err| request_error(err.to_string()))?;
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT)).map_err(|err| request_error(err.to_string()))?;
+
+    let handshake_error = |err: String| (FailureReason::TlsHandshake, format!("TLS handshake failed: {}", err));
+    let connector = SslConnector::builder(SslMethod::tls())
+        .map_err(|err| handshake_error(err.to_string()))?
+        .build();
+    let mut config = connector.configure().map_err(|err| handshake_error(err.to_string()))?;
+    config.set_verify(SslVerifyMode::NONE);
+    let tls = config.connect(&server_name, stream).map_err(|err| handshake_error(err.to_string()))?;
+
+    let session = tls.ssl();
+    let cert = session.peer_certificate()
+        .ok_or_else(|| handshake_error("the server didn't send a certificate".to_string()))?;
+    let mut info = certificate_info(&cert, session.version_str()).map_err(handshake_error)?;
+
+    let thresholds = ssl.expiry_warning_days.clone().unwrap_or(DEFAULT_EXPIRY_WARNING_DAYS.to_vec());
+    info.warning_threshold = warning_threshold(info.days_remaining, &thresholds);
+
+    let verify = session.verify_result();
+    if verify == X509VerifyResult::OK {
+        return Ok((info, None));
+    }
+    let error = verify_error(verify, &server_name, &info);
+    Ok((info, Some(error)))
+}
+
+pub async fn check_certificate(ssl: &Ssl, mut result: CheckResult) -> CheckResult {
+    let ssl_ref = ssl.clone();
+    let probe_result = tokio::task::spawn_blocking(move || probe(&ssl_ref))
+        .await
+        .expect("Certificate validation task failed.");
+
+    match probe_result {
+        Ok((info, verify_error)) => {
+            result.certificate = Some(info.clone());
+            if let Some((reason, error)) = verify_error {
+                println!("Error with cert, url: {}. {}", ssl.url, error);
+                return result.fail(reason, error);
+            }
+            if info.warning_threshold.is_some() {
+                println!("Cert for url [{}] expires in {} days.", ssl.url, info.days_remaining);
+                return result.degrade(
+                    FailureReason::CertificateExpiry,
+                    format!("certificate expires in {} days", info.days_remaining),
+                );
+            }
+            println!("Cert for url [{}] is ok.", ssl.url);
+            result
+        }
+        Err((reason, error)) => {
+            println!("Error with cert, url: {}. {}", ssl.url, error);
+            result.fail(reason, error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::ssl::{SslAcceptor, SslMethod};
+    use openssl::x509::extension::SubjectAlternativeName;
+    use openssl::x509::{X509NameBuilder, X509};
+
+    use super::{check_certificate, warning_threshold, Ssl, DEFAULT_EXPIRY_WARNING_DAYS};
+    use crate::monitor::state::CheckStatus;
+    use crate::monitor::website::models::{CheckKind, CheckResult, FailureReason};
+
+    #[test]
+    fn warning_threshold_test() {
+        assert_eq!(warning_threshold(64, &DEFAULT_EXPIRY_WARNING_DAYS), None);
+        assert_eq!(warning_threshold(30, &DEFAULT_EXPIRY_WARNING_DAYS), Some(30));
+        assert_eq!(warning_threshold(12, &DEFAULT_EXPIRY_WARNING_DAYS), Some(14));
+        assert_eq!(warning_threshold(0, &DEFAULT_EXPIRY_WARNING_DAYS), Some(1));
+        assert_eq!(warning_threshold(5, &[]), None);
+    }
+
+    /// Start a TLS server with a self-signed certificate for `localhost.test` in a random port.
+    fn start_self_signed_server() -> u16 {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "localhost.test").unwrap();
+        let name = name.build();
+
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(90).unwrap()).unwrap();
+        let san = SubjectAlternativeName::new().dns("localhost.test").build(&cert.x509v3_context(None, None)).unwrap();
+        cert.append_extension(san).unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+        let cert = cert.build();
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_private_key(&key).unwrap();
+        acceptor.set_certificate(&cert).unwrap();
+        let acceptor = acceptor.build();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Some(Ok(stream)) = listener.incoming().next() {
+                let _ = acceptor.accept(stream);
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn self_signed_certificate_test() {
+        let port = start_self_signed_server();
+        let ssl = Ssl {
+            url: "127.0.0.1".to_string(),
+            port: Some(port),
+            sni: Some("localhost.test".to_string()),
+            ..Default::default()
+        };
+        let result = CheckResult::new(ssl.id(), CheckKind::Certificate, ssl.url.clone());
+
+        let result = check_certificate(&ssl, result).await;
+
+        assert_eq!(result.check_id, format!("SSL 127.0.0.1:{}", port));
+        assert_eq!(result.status, CheckStatus::Down);
+        assert_eq!(result.reason, Some(FailureReason::SelfSignedCertificate));
+        let cert = result.certificate.unwrap();
+        assert_eq!(cert.sans, vec!["localhost.test".to_string()]);
+        assert_eq!(cert.issuer, "localhost.test");
+        assert!(cert.tls_version.starts_with("TLSv1"));
+        assert!((89..=90).contains(&cert.days_remaining));
+    }
+
+    #[tokio::test]
+    async fn connection_error_test() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let ssl = Ssl { url: "127.0.0.1".to_string(), port: Some(port), ..Default::default() };
+        let result = CheckResult::new(ssl.id(), CheckKind::Certificate, ssl.url.clone());
+
+        let result = check_certificate(&ssl, result).await;
+
+        assert_eq!(result.reason, Some(FailureReason::Request));
+    }
+}
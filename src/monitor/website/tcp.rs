@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+use crate::monitor::website::models::{CheckResult, CheckSettings, FailureReason};
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5000;
+
+/// Test of a raw TCP service (databases, caches, smtp relays, ...).
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Tcp {
+    pub host: String,
+    pub port: u16,
+    /// Max time (in milliseconds) to connect, and to receive the expected banner.
+    pub connect_timeout: Option<u64>,
+    /// Data sent to the service after the connection.
+    pub send: Option<String>,
+    /// Data that the service has to answer, ex: the `220` of a smtp server.
+    pub expect: Option<String>,
+    #[serde(flatten)]
+    pub settings: CheckSettings,
+}
+
+impl Tcp {
+    pub fn id(&self) -> String {
+        format!("TCP {}:{}", self.host, self.port)
+    }
+
+    pub fn target(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+pub async fn check_tcp(tcp: &Tcp, mut result: CheckResult) -> CheckResult {
+    let max_time = Duration::from_millis(tcp.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS));
+    let start = Instant::now();
+
+    let connection = timeout(max_time, TcpStream::connect((tcp.host.as_str(), tcp.port))).await;
+    result.latency = Some(start.elapsed());
+    let mut stream = match connection {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(err)) => return result.fail(FailureReason::Request, format!("connection failed: {}", err)),
+        Err(_) => {
+            return result.fail(
+                FailureReason::Request,
+                format!("connection timeout after {} ms", max_time.as_millis()),
+            );
+        }
+    };
+
+    if let Some(ref data) = tcp.send {
+        if let Err(err) = stream.write_all(data.as_bytes()).await {
+            return result.fail(FailureReason::Request, format!("error sending data: {}", err));
+        }
+    }
+
+    if let Some(ref expected) = tcp.expect {
+        let deadline = start + max_time;
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !String::from_utf8_lossy(&received).contains(expected.as_str()) {
+            match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+                Ok(Ok(0)) | Err(_) => {
+                    return result.fail(
+                        FailureReason::Banner,
+                        format!(
+                            "expected \"{}\" but received \"{}\"",
+                            expected,
+                            String::from_utf8_lossy(&received).trim()
+                        ),
+                    );
+                }
+                Ok(Ok(size)) => received.extend_from_slice(&buffer[..size]),
+                Ok(Err(err)) => return result.fail(FailureReason::Request, format!("error reading data: {}", err)),
+            }
+        }
+        result.latency = Some(start.elapsed());
+    }
+
+    println!("TCP [{}] is OK.", tcp.target());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::{check_tcp, Tcp};
+    use crate::monitor::state::CheckStatus;
+    use crate::monitor::website::models::{CheckKind, CheckResult, FailureReason};
+
+    /// Start a server that answers `+PONG` to a `PING`, like redis.
+    async fn start_ping_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0u8; 64];
+                if let Ok(size) = stream.read(&mut buffer).await {
+                    if &buffer[..size] == b"PING\r\n" {
+                        let _ = stream.write_all(b"+PONG\r\n").await;
+                    }
+                }
+            }
+        });
+        port
+    }
+
+    fn new_result(tcp: &Tcp) -> CheckResult {
+        CheckResult::new(tcp.id(), CheckKind::Tcp, tcp.target())
+    }
+
+    #[tokio::test]
+    async fn banner_test() {
+        let port = start_ping_server().await;
+        let tcp = Tcp {
+            host: "127.0.0.1".to_string(),
+            port,
+            send: Some("PING\r\n".to_string()),
+            expect: Some("+PONG".to_string()),
+            ..Default::default()
+        };
+        let result = check_tcp(&tcp, new_result(&tcp)).await;
+        assert_eq!(result.status, CheckStatus::Up);
+
+        let tcp = Tcp { send: Some("HELLO\r\n".to_string()), connect_timeout: Some(200), ..tcp };
+        let result = check_tcp(&tcp, new_result(&tcp)).await;
+        assert_eq!(result.reason, Some(FailureReason::Banner));
+    }
+
+    #[tokio::test]
+    async fn connection_refused_test() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let tcp = Tcp { host: "127.0.0.1".to_string(), port, ..Default::default() };
+
+        let result = check_tcp(&tcp, new_result(&tcp)).await;
+
+        assert_eq!(result.status, CheckStatus::Down);
+        assert_eq!(result.reason, Some(FailureReason::Request));
+    }
+}
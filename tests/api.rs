@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
 use mockall::predicate::eq;
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use reqwest::StatusCode;
@@ -11,6 +12,9 @@ use tokio::task::JoinHandle;
 use sm::monitor::api::ApiService;
 use sm::config::Config;
 use sm::monitor::telegram::{MockTelegramServiceTrait, TelegramServiceTrait};
+use sm::monitor::state::CheckStatus;
+use sm::monitor::website::heartbeat::{Heartbeat, HeartbeatRegistry};
+use sm::monitor::website::models::{CheckKind, CheckResult, FailureReason};
 
 fn get_default_test_config(port: Option<u32>) -> Config {
     Config {
@@ -20,12 +24,25 @@ fn get_default_test_config(port: Option<u32>) -> Config {
         frontend_tests: Some(Vec::new()),
         website_monitor_timeout: Some(20),
         ssl_tests: Some(Vec::new()),
+        tcp_tests: Some(Vec::new()),
+        dns_tests: Some(Vec::new()),
+        scenario_tests: Some(Vec::new()),
+        sitemap_tests: Some(Vec::new()),
+        heartbeats: Some(Vec::new()),
+        command_tests: Some(Vec::new()),
         pause_reminder_timeout: Some(86400),
         times_to_retry: Some(5),
+        max_concurrent_checks: Some(10),
+        max_concurrent_checks_per_host: Some(2),
+        flap_window: Some(20),
+        flap_high_threshold: Some(30.0),
+        flap_low_threshold: Some(10.0),
         // telegram
         enable_telegram: Some(false),
         telegram_bot_token: None,
         groups: Some(Vec::new()),
+        routing: Some(Vec::new()),
+        escalation_policies: Some(Vec::new()),
         // api
         enable_api: Some(true),
         host: Some("127.0.0.1".to_string()),
@@ -35,11 +52,20 @@ fn get_default_test_config(port: Option<u32>) -> Config {
 }
 
 fn start_api_service(config: Config, telegram_service: Arc<Mutex<dyn TelegramServiceTrait + Send>>) -> (JoinHandle<()>, Runtime, Sender<()>) {
+    let heartbeats = HeartbeatRegistry::new(config.heartbeats.as_deref().unwrap_or_default(), SystemTime::now());
+    start_api_service_with_heartbeats(config, telegram_service, heartbeats)
+}
+
+fn start_api_service_with_heartbeats(
+    config: Config,
+    telegram_service: Arc<Mutex<dyn TelegramServiceTrait + Send>>,
+    heartbeats: HeartbeatRegistry,
+) -> (JoinHandle<()>, Runtime, Sender<()>) {
     let rt = Runtime::new().unwrap();
 
     let (tx, rx) = tokio::sync::oneshot::channel();
     let api_thread = rt.spawn(async move {
-        let api_service = ApiService::new(config, telegram_service);
+        let api_service = ApiService::new(config, telegram_service, heartbeats);
         api_service.start_api(Some(rx)).await;
         println!("API service finished");
     });
@@ -49,7 +75,7 @@ fn start_api_service(config: Config, telegram_service: Arc<Mutex<dyn TelegramSer
 
 fn get_url(config: Config) -> String {
     let host = config.host.clone().unwrap();
-    let port = config.port.clone().unwrap();
+    let port = config.port.unwrap();
     format!("http://{}:{}/notification", host, port)
 }
 
@@ -86,7 +112,7 @@ async fn test_send_notification_flow() {
 
     // stop api service
     println!("Sending kill signal to api service");
-    if let Err(_) = tx.send(()) {
+    if tx.send(()).is_err() {
         panic!("Failed to send kill signal to api service");
     }
     api_thread.await.expect("Failed to join api thread");
@@ -118,7 +144,47 @@ async fn test_authorization_api_fail() {
 
     // stop api service
     println!("Sending kill signal to api service");
-    if let Err(_) = tx.send(()) {
+    if tx.send(()).is_err() {
+        panic!("Failed to send kill signal to api service");
+    }
+    api_thread.await.expect("Failed to join api thread");
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn test_heartbeat_pings() {
+    let mut config_ref = get_default_test_config(Some(8355));
+    let backup = Heartbeat { name: "backup".to_string(), period: 3600, ..Default::default() };
+    config_ref.heartbeats = Some(vec![backup.clone()]);
+    let heartbeats = HeartbeatRegistry::new(config_ref.heartbeats.as_deref().unwrap_or_default(), SystemTime::now());
+
+    let (api_thread, rt, tx) = start_api_service_with_heartbeats(
+        config_ref.clone(),
+        Arc::new(Mutex::new(MockTelegramServiceTrait::new())),
+        heartbeats.clone(),
+    );
+
+    let ping = |path: &str| reqwest::Client::new()
+        .post(format!("http://127.0.0.1:8355/heartbeat/{}", path))
+        .header(AUTHORIZATION, "Basic dGVzdA==")
+        .send();
+    assert_eq!(ping("backup/start").await.unwrap().status(), StatusCode::OK);
+    assert_eq!(ping("backup/fail").await.unwrap().status(), StatusCode::OK);
+    assert_eq!(ping("backup/other").await.unwrap().status(), StatusCode::NOT_FOUND);
+    assert_eq!(ping("unknown").await.unwrap().status(), StatusCode::NOT_FOUND);
+
+    let check = || heartbeats.check(
+        &backup,
+        CheckResult::new(backup.id(), CheckKind::Heartbeat, backup.name.clone()),
+        SystemTime::now(),
+    );
+    assert_eq!(check().reason, Some(FailureReason::HeartbeatFailed));
+
+    assert_eq!(ping("backup").await.unwrap().status(), StatusCode::OK);
+    assert_eq!(check().status, CheckStatus::Up);
+
+    // stop api service
+    if tx.send(()).is_err() {
         panic!("Failed to send kill signal to api service");
     }
     api_thread.await.expect("Failed to join api thread");
@@ -1,14 +1,28 @@
 use std::sync::Arc;
 
-use httpmock::Method::GET;
+use httpmock::Method::{GET, POST, PUT};
 use httpmock::MockServer;
+use reqwest::Client;
 use tokio::runtime::Runtime;
 use tokio::sync::Mutex;
 
 use sm::config::Config;
 use sm::monitor::Monitor;
+use sm::monitor::escalation::{EscalationPolicy, EscalationStep};
+use sm::monitor::routing::RoutingRule;
 use sm::monitor::telegram::MockTelegramServiceTrait;
-use sm::monitor::website::{Get, RouteTest};
+use sm::monitor::telegram::models::{CallbackQuery, Chat, Message, Update, User};
+use sm::monitor::notification::result_message;
+use sm::monitor::state::CheckStatus;
+use sm::monitor::website::models::{CheckKind, FailureReason};
+use sm::monitor::website::assertions::{ExpectedStatus, ResponseAssertions};
+use sm::monitor::website::auth::Auth;
+use sm::monitor::website::content::ContentChange;
+use sm::monitor::website::crawler::Crawl;
+use sm::monitor::website::retry::{Backoff, RetryPolicy};
+use sm::monitor::website::scenario::{Capture, Scenario, Step};
+use sm::monitor::website::sitemap::Sitemap;
+use sm::monitor::website::{Get, Http, RouteTest, WebsiteService};
 
 fn get_default_test_config(port: Option<u32>) -> Config {
     Config {
@@ -16,14 +30,27 @@ fn get_default_test_config(port: Option<u32>) -> Config {
         enable_service_monitor: Some(true),
         api_tests: Some(Vec::new()),
         frontend_tests: Some(Vec::new()),
-        website_monitor_timeout: Some(0),
+        website_monitor_timeout: Some(1),
         ssl_tests: Some(Vec::new()),
+        tcp_tests: Some(Vec::new()),
+        dns_tests: Some(Vec::new()),
+        scenario_tests: Some(Vec::new()),
+        sitemap_tests: Some(Vec::new()),
+        heartbeats: Some(Vec::new()),
+        command_tests: Some(Vec::new()),
         pause_reminder_timeout: Some(86400),
         times_to_retry: Some(5),
+        max_concurrent_checks: Some(10),
+        max_concurrent_checks_per_host: Some(2),
+        flap_window: Some(20),
+        flap_high_threshold: Some(30.0),
+        flap_low_threshold: Some(10.0),
         // telegram
         enable_telegram: Some(false),
         telegram_bot_token: None,
         groups: Some(Vec::new()),
+        routing: Some(Vec::new()),
+        escalation_policies: Some(Vec::new()),
         // api
         enable_api: Some(false),
         host: Some("127.0.0.1".to_string()),
@@ -58,7 +85,8 @@ async fn test_authorization_api_fail() {
     let mut config_ref = get_default_test_config(Some(8354));
     config_ref.api_tests = Some(vec![
         RouteTest::GET(Get {
-            url: mock_server.url("/mock_endpoint")
+            url: mock_server.url("/mock_endpoint"),
+            ..Default::default()
         })
     ]);
 
@@ -73,8 +101,552 @@ async fn test_authorization_api_fail() {
     });
 
     wait_action(|| {
-        return mock_endpoint.hits() > 1;
+        mock_endpoint.hits() > 1
     });
 
     rt.shutdown_background();
 }
+
+#[tokio::test]
+async fn test_slow_check_does_not_delay_the_others() {
+    let rt = Runtime::new().unwrap();
+
+    let mock_server = MockServer::start();
+    let slow = mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/slow");
+        then.status(200)
+            .delay(std::time::Duration::from_secs(5));
+    });
+    let fast = mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/fast");
+        then.status(200);
+    });
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.api_tests = Some(vec![
+        RouteTest::GET(Get {
+            url: mock_server.url("/slow"),
+            ..Default::default()
+        }),
+        RouteTest::GET(Get {
+            url: mock_server.url("/fast"),
+            ..Default::default()
+        }),
+    ]);
+
+    let monitor = Monitor::new(config_ref, Some(Arc::new(Mutex::new(MockTelegramServiceTrait::new()))));
+    let start = std::time::Instant::now();
+    rt.spawn(async move {
+        monitor.start().await;
+    });
+
+    // the fast check runs every second while the slow one is waiting for its response
+    wait_action(|| fast.hits() >= 3);
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(slow.hits(), 1);
+
+    rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn test_acknowledge_button_stops_the_escalation() {
+    let rt = Runtime::new().unwrap();
+
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/down");
+        then.status(500);
+    });
+    let check_id = format!("GET {}", mock_server.url("/down"));
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.enable_telegram = Some(true);
+    config_ref.times_to_retry = Some(1);
+    config_ref.api_tests = Some(vec![
+        RouteTest::GET(Get {
+            url: mock_server.url("/down"),
+            ..Default::default()
+        })
+    ]);
+    config_ref.groups = Some(vec![-100]);
+    config_ref.routing = Some(vec![RoutingRule {
+        check_ids: Some(vec![check_id.clone()]),
+        chats: vec![-100],
+        escalation: Some("on-call".to_string()),
+        ..Default::default()
+    }]);
+    config_ref.escalation_policies = Some(vec![EscalationPolicy {
+        name: "on-call".to_string(),
+        steps: vec![EscalationStep { after: 10, chats: None }],
+    }]);
+
+    // the buttons are pressed once the alert is sent
+    let alerts: Arc<std::sync::Mutex<Vec<String>>> = Arc::default();
+    let edits: Arc<std::sync::Mutex<Vec<(i64, i64, String)>>> = Arc::default();
+    let answers: Arc<std::sync::Mutex<Vec<(String, String)>>> = Arc::default();
+    let mut telegram = MockTelegramServiceTrait::new();
+    telegram.expect_sync_commands().returning(|| {});
+    telegram.expect_get_commands().returning(Vec::new);
+    telegram.expect_send_pendings_messages().returning(|| {});
+    telegram.expect_send_message().returning(|_, _| {});
+    let alerts_ref = alerts.clone();
+    telegram.expect_send_message_with_keyboard().returning(move |text, _, _| {
+        alerts_ref.lock().unwrap().push(text);
+        vec![(-100, 7)]
+    });
+    let alerts_ref = alerts.clone();
+    let mut pressed = false;
+    telegram.expect_get_all_updates().returning(move || {
+        if pressed || alerts_ref.lock().unwrap().is_empty() {
+            return Vec::new();
+        }
+        pressed = true;
+        let press = |id: &str, username: &str| Update {
+            update_id: 1,
+            message: None,
+            callback_query: Some(CallbackQuery {
+                id: id.to_string(),
+                from: User { id: 1, first_name: username.to_string(), username: Some(username.to_string()) },
+                message: Some(Message { message_id: 7, from: None, chat: Chat { id: -100 }, text: None, entities: None }),
+                data: Some("ack:1".to_string()),
+            }),
+        };
+        vec![press("q1", "guille"), press("q2", "ana")]
+    });
+    let edits_ref = edits.clone();
+    telegram.expect_edit_message().returning(move |chat_id, message_id, text| {
+        edits_ref.lock().unwrap().push((chat_id, message_id, text));
+    });
+    let answers_ref = answers.clone();
+    telegram.expect_answer_callback_query().returning(move |id, text| {
+        answers_ref.lock().unwrap().push((id, text));
+    });
+
+    let monitor = Monitor::new(config_ref, Some(Arc::new(Mutex::new(telegram))));
+    let escalations = monitor.escalations();
+    rt.spawn(async move {
+        monitor.start().await;
+    });
+
+    wait_action(|| answers.lock().unwrap().len() == 2);
+    rt.shutdown_background();
+
+    let alerts = alerts.lock().unwrap().clone();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(
+        *edits.lock().unwrap(),
+        vec![(-100, 7, format!("{}\n✅ Acknowledged by @guille", alerts[0]))],
+    );
+    assert_eq!(*answers.lock().unwrap(), vec![
+        ("q1".to_string(), "✅ Acknowledged by @guille".to_string()),
+        ("q2".to_string(), "⚠️ the alert was already acknowledged by @guille".to_string()),
+    ]);
+    // the escalation of the acknowledged alert doesn't have more steps
+    assert_eq!(escalations.lock().await.check_id(1), Some(check_id));
+    assert_eq!(escalations.lock().await.time_to_next(std::time::Instant::now()), None);
+}
+
+#[tokio::test]
+async fn test_check_result_of_failing_endpoint() {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/broken_endpoint");
+        then.status(500);
+    });
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.frontend_tests = Some(vec![
+        RouteTest::GET(Get {
+            url: mock_server.url("/broken_endpoint"),
+            ..Default::default()
+        })
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result.check_id, format!("GET {}", mock_server.url("/broken_endpoint")));
+    assert_eq!(result.kind, CheckKind::Frontend);
+    assert_eq!(result.status, CheckStatus::Down);
+    assert_eq!(result.http_code, Some(500));
+    assert!(result.latency.is_some());
+    assert!(result.error.is_some());
+}
+
+#[tokio::test]
+async fn test_response_assertions() {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/no_content");
+        then.status(204);
+    });
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/maintenance");
+        then.status(200)
+            .body("Site under maintenance");
+    });
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/large");
+        then.status(200)
+            .body("a".repeat(100_000));
+    });
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.frontend_tests = Some(vec![
+        RouteTest::GET(Get {
+            url: mock_server.url("/no_content"),
+            assertions: ResponseAssertions {
+                expected_status: Some(vec![ExpectedStatus::Range("2xx".to_string())]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        RouteTest::GET(Get {
+            url: mock_server.url("/maintenance"),
+            assertions: ResponseAssertions {
+                body_contains: Some(vec!["Binary Coffee".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        RouteTest::GET(Get {
+            url: mock_server.url("/large"),
+            assertions: ResponseAssertions {
+                max_body_size: Some(100),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    assert_eq!(results[0].status, CheckStatus::Up);
+    assert_eq!(results[1].status, CheckStatus::Down);
+    assert_eq!(results[1].error, Some("body doesn't contain \"Binary Coffee\"".to_string()));
+    assert_eq!(results[2].error, Some("body size exceeds the max of 100 bytes".to_string()));
+}
+
+#[tokio::test]
+async fn test_http_test_with_headers_and_auth() {
+    std::env::set_var("SM_TEST_API_TOKEN", "secret_token");
+
+    let mock_server = MockServer::start();
+    let mock_endpoint = mock_server.mock(|when, then| {
+        when.method(PUT)
+            .path("/posts/1")
+            .header("authorization", "Bearer secret_token")
+            .header("x-client", "service-monitor")
+            .body("{\"title\": \"new\"}");
+        then.status(204);
+    });
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.api_tests = Some(vec![
+        RouteTest::HTTP(Http {
+            url: mock_server.url("/posts/1"),
+            method: "put".to_string(),
+            headers: Some([("X-Client".to_string(), "service-monitor".to_string())].into_iter().collect()),
+            body: Some("{\"title\": \"new\"}".to_string()),
+            auth: Some(Auth::Bearer { token: "${SM_TEST_API_TOKEN}".to_string() }),
+            assertions: ResponseAssertions {
+                expected_status: Some(vec![ExpectedStatus::Code(204)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    mock_endpoint.assert();
+    assert_eq!(results[0].check_id, format!("PUT {}", mock_server.url("/posts/1")));
+    assert_eq!(results[0].status, CheckStatus::Up);
+}
+
+#[tokio::test]
+async fn test_latency_thresholds() {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/slow_endpoint");
+        then.status(200)
+            .delay(std::time::Duration::from_millis(300));
+    });
+
+    let slow_test = |warn_latency_ms, critical_latency_ms| RouteTest::GET(Get {
+        url: mock_server.url("/slow_endpoint"),
+        assertions: ResponseAssertions {
+            warn_latency_ms,
+            critical_latency_ms,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let mut config_ref = get_default_test_config(None);
+    config_ref.api_tests = Some(vec![
+        slow_test(Some(100), Some(10000)),
+        slow_test(Some(100), Some(200)),
+        slow_test(None, None),
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    assert_eq!(results[0].status, CheckStatus::Degraded);
+    assert_eq!(results[1].status, CheckStatus::Down);
+    assert_eq!(results[2].status, CheckStatus::Up);
+    let timings = results[2].timings.clone().unwrap();
+    assert!(timings.ttfb.unwrap() >= std::time::Duration::from_millis(300));
+    assert_eq!(timings.total, results[2].latency);
+}
+
+#[tokio::test]
+async fn test_scenario_with_captured_values() {
+    let mock_server = MockServer::start();
+    let login = mock_server.mock(|when, then| {
+        when.method(POST)
+            .path("/login");
+        then.status(200)
+            .header("Set-Cookie", "session=abc; HttpOnly")
+            .json_body(serde_json::json!({ "jwt": "token_123", "user": { "id": 7 } }));
+    });
+    let profile = mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/users/7")
+            .header("authorization", "Bearer token_123")
+            .header("cookie", "session=abc");
+        then.status(200);
+    });
+
+    let login_step = Step {
+        method: Some("post".to_string()),
+        url: mock_server.url("/login"),
+        capture: Some([
+            ("token".to_string(), Capture::Json { path: "$.jwt".to_string() }),
+            ("user".to_string(), Capture::Json { path: "$.user.id".to_string() }),
+            ("session".to_string(), Capture::Cookie { name: "session".to_string() }),
+        ].into_iter().collect()),
+        ..Default::default()
+    };
+    let profile_step = |path: &str| Step {
+        url: format!("{}{}", mock_server.base_url(), path),
+        headers: Some([("Cookie".to_string(), "session={{session}}".to_string())].into_iter().collect()),
+        auth: Some(Auth::Bearer { token: "{{token}}".to_string() }),
+        ..Default::default()
+    };
+    let mut config_ref = get_default_test_config(None);
+    config_ref.scenario_tests = Some(vec![
+        Scenario {
+            name: "profile".to_string(),
+            steps: vec![login_step.clone(), profile_step("/users/{{user}}")],
+            ..Default::default()
+        },
+        Scenario {
+            name: "missing profile".to_string(),
+            steps: vec![login_step, profile_step("/users/{{user}}/posts")],
+            ..Default::default()
+        },
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    login.assert_hits(2);
+    profile.assert();
+    assert_eq!(results[0].check_id, "SCENARIO profile");
+    assert_eq!(results[0].kind, CheckKind::Scenario);
+    assert_eq!(results[0].status, CheckStatus::Up);
+    assert_eq!(results[1].status, CheckStatus::Down);
+    assert_eq!(results[1].reason, Some(FailureReason::Status));
+    assert_eq!(
+        results[1].error.as_deref(),
+        Some(format!("step 2 (GET {}/users/7/posts): unexpected status 404 Not Found", mock_server.base_url()).as_str()),
+    );
+}
+
+#[tokio::test]
+async fn test_sitemap_reports_broken_pages() {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/sitemap");
+        then.status(200)
+            .json_body(serde_json::json!(["/", "/post/hello", "/post/removed", "/users/guille"]));
+    });
+    for path in ["/", "/post/hello", "/users/guille"] {
+        mock_server.mock(|when, then| {
+            when.method(GET)
+                .path(path);
+            then.status(200);
+        });
+    }
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.sitemap_tests = Some(vec![
+        Sitemap {
+            url: mock_server.url("/api/sitemap"),
+            ..Default::default()
+        },
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    assert_eq!(results[0].kind, CheckKind::Sitemap);
+    assert_eq!(results[0].status, CheckStatus::Down);
+    assert_eq!(results[0].reason, Some(FailureReason::BrokenPages));
+    assert_eq!(
+        results[0].error.as_deref(),
+        Some(format!("1 of 4 pages are broken: {} (404)", mock_server.url("/post/removed")).as_str()),
+    );
+}
+
+#[tokio::test]
+async fn test_crawl_reports_broken_links_by_page() {
+    let mock_server = MockServer::start();
+    let page = |path: &'static str, html: &'static str| mock_server.mock(move |when, then| {
+        when.method(GET)
+            .path(path);
+        then.status(200)
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(html);
+    });
+    page("/", r#"<a href="/post/hello">hello</a><img src="/missing.png"><a href="https://github.com">github</a>"#);
+    page("/post/hello", r#"<a href="/">home</a><a href="/post/removed">removed</a>"#);
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/post/removed");
+        then.status(404);
+    });
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.frontend_tests = Some(vec![
+        RouteTest::GET(Get {
+            url: mock_server.url("/"),
+            crawl: Some(Crawl::default()),
+            ..Default::default()
+        }),
+    ]);
+    let service = WebsiteService::new(config_ref);
+    assert_eq!(service.checks().len(), 2);
+
+    let results = service.crawls_vitaly().await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].check_id, format!("CRAWL {}", mock_server.url("/")));
+    assert_eq!(results[0].reason, Some(FailureReason::BrokenLinks));
+    assert_eq!(
+        results[0].error.as_deref(),
+        Some(format!(
+            "2 broken links in 2 pages: {} -> {} (404); {} -> {} (404)",
+            mock_server.url("/"), mock_server.url("/missing.png"),
+            mock_server.url("/post/hello"), mock_server.url("/post/removed"),
+        ).as_str()),
+    );
+}
+
+#[tokio::test]
+async fn test_content_change_is_reported_with_diff() {
+    let mock_server = MockServer::start();
+    let mut page = mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/");
+        then.status(200)
+            .body("<h1>Binary Coffee</h1>\n<p>updated at 10:00</p>\n");
+    });
+
+    let mut config_ref = get_default_test_config(None);
+    config_ref.frontend_tests = Some(vec![
+        RouteTest::GET(Get {
+            url: mock_server.url("/"),
+            content_change: Some(ContentChange {
+                ignore_regex: Some(vec![r"\d+:\d+".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    ]);
+    let service = WebsiteService::new(config_ref);
+    let check = service.checks().remove(0);
+    let client = Client::new();
+    let check_id = format!("GET {}", mock_server.url("/"));
+
+    // the manual checks don't set the baseline
+    assert_eq!(service.summary().await[0].status, CheckStatus::Up);
+    assert!(!service.accept_content(&check_id));
+    assert_eq!(service.run_scheduled_check(&check, &client).await.status, CheckStatus::Up);
+
+    page.delete();
+    mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/");
+        then.status(200)
+            .body("<h1>Hacked</h1>\n<p>updated at 10:05</p>\n");
+    });
+    let results = service.summary().await;
+
+    assert_eq!(results[0].reason, Some(FailureReason::ContentChanged));
+    assert_eq!(
+        results[0].diff.as_deref(),
+        Some("--- baseline\n+++ current\n@@ -1,2 +1,2 @@\n-<h1>Binary Coffee</h1>\n+<h1>Hacked</h1>\n <p>updated at </p>"),
+    );
+    // the check is down until the change is accepted
+    assert_eq!(service.frontend_vitaly().await[0].reason, Some(FailureReason::ContentChanged));
+    assert_eq!(service.summary().await[0].status, CheckStatus::Down);
+
+    assert!(service.accept_content(&check_id));
+    assert_eq!(service.summary().await[0].status, CheckStatus::Up);
+    assert!(!service.accept_content(&check_id));
+    assert_eq!(service.run_scheduled_check(&check, &client).await.status, CheckStatus::Up);
+    assert!(service.accept_content(&check_id));
+}
+
+#[tokio::test]
+async fn test_retry_policy() {
+    let mock_server = MockServer::start();
+    let unavailable = mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/unavailable");
+        then.status(503);
+    });
+    let hung = mock_server.mock(|when, then| {
+        when.method(GET)
+            .path("/hung");
+        then.status(200)
+            .delay(std::time::Duration::from_millis(500));
+    });
+
+    let test = |path: &str, retry: RetryPolicy| RouteTest::GET(Get {
+        url: mock_server.url(path),
+        retry,
+        ..Default::default()
+    });
+    let backoff = Some(Backoff { delay: Some(10), ..Default::default() });
+    let mut config_ref = get_default_test_config(None);
+    config_ref.api_tests = Some(vec![
+        test("/unavailable", RetryPolicy {
+            retries: Some(2),
+            retry_on_status: Some(true),
+            backoff: backoff.clone(),
+            ..Default::default()
+        }),
+        test("/hung", RetryPolicy { timeout: Some(100), retries: Some(1), backoff, ..Default::default() }),
+    ]);
+
+    let results = WebsiteService::new(config_ref).summary().await;
+
+    unavailable.assert_hits(3);
+    assert_eq!(results[0].attempts, Some(3));
+    assert_eq!(results[0].reason, Some(FailureReason::Status));
+    assert!(result_message(&results[0]).ends_with(" after 3 attempts\\."));
+
+    hung.assert_hits(2);
+    assert_eq!(results[1].attempts, Some(2));
+    assert_eq!(results[1].reason, Some(FailureReason::Request));
+}